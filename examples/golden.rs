@@ -0,0 +1,110 @@
+//! Renders a known pattern offscreen and compares it against a reference image.
+//!
+//! Needs a GPU, so it is not part of the default build:
+//! `cargo run --example golden --features image`
+
+use std::process::ExitCode;
+
+use glow::HasContext;
+use glutin::{
+    context::ContextAttributesBuilder,
+    prelude::{GlDisplay, NotCurrentGlContextSurfaceAccessor},
+};
+use glutin2_sharing::{
+    capture::{diff_rgba, load_rgba, read_rgba},
+    create_surface, load_gl, select_display_config,
+    winit::{event_loop::EventLoop, window::WindowBuilder},
+    ColorDepth, DisplayConfig, Rect,
+};
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+
+const REFERENCE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/examples/reference/quadrants.png"
+);
+const TOLERANCE: u8 = 2;
+
+fn draw_quadrants(gl: &glow::Context, width: u32, height: u32) {
+    let (hw, hh) = (width / 2, height / 2);
+    // gl origin is bottom left, the reference image is top left
+    let quadrants = [
+        (Rect::new(0, hh as i32, hw, hh), [1.0, 0.0, 0.0]),
+        (Rect::new(hw as i32, hh as i32, hw, hh), [0.0, 1.0, 0.0]),
+        (Rect::new(0, 0, hw, hh), [0.0, 0.0, 1.0]),
+        (Rect::new(hw as i32, 0, hw, hh), [1.0, 1.0, 1.0]),
+    ];
+    unsafe {
+        gl.enable(glow::SCISSOR_TEST);
+        for (rect, [r, g, b]) in quadrants {
+            rect.scissor(gl);
+            gl.clear_color(r, g, b, 1.0);
+            gl.clear(glow::COLOR_BUFFER_BIT);
+        }
+        gl.disable(glow::SCISSOR_TEST);
+    }
+}
+
+fn main() -> ExitCode {
+    let (reference, width, height) = load_rgba(REFERENCE).expect("failed to load reference");
+
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_visible(false)
+        .build(&event_loop)
+        .unwrap();
+    let raw_wnd = window.raw_window_handle();
+
+    let DisplayConfig {
+        display, config, ..
+    } = select_display_config(
+        event_loop.raw_display_handle(),
+        raw_wnd,
+        ColorDepth::Srgb8,
+        0,
+        Some((0, 0)),
+    )
+    .unwrap();
+    let surface = create_surface(1, 1, &display, &config, raw_wnd).unwrap();
+    let c = unsafe {
+        display
+            .create_context(&config, &ContextAttributesBuilder::new().build())
+            .unwrap()
+    }
+    .make_current(&surface)
+    .unwrap();
+    let gl = load_gl(&c);
+
+    let pixels = unsafe {
+        let rb = gl.create_renderbuffer().unwrap();
+        gl.bind_renderbuffer(glow::RENDERBUFFER, Some(rb));
+        gl.renderbuffer_storage(glow::RENDERBUFFER, glow::RGBA8, width as _, height as _);
+        let fb = gl.create_framebuffer().unwrap();
+        gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+        gl.framebuffer_renderbuffer(
+            glow::FRAMEBUFFER,
+            glow::COLOR_ATTACHMENT0,
+            glow::RENDERBUFFER,
+            Some(rb),
+        );
+        Rect::from_size(width, height).viewport(&gl);
+
+        draw_quadrants(&gl, width, height);
+        let pixels = read_rgba(&gl, width, height);
+
+        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+        gl.delete_framebuffer(fb);
+        gl.delete_renderbuffer(rb);
+        pixels
+    };
+
+    let diff = diff_rgba(&pixels, &reference).unwrap();
+    println!(
+        "max difference: {}, mean difference: {:.4}",
+        diff.max, diff.mean
+    );
+    if diff.max > TOLERANCE {
+        eprintln!("output differs from {}", REFERENCE);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
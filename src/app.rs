@@ -0,0 +1,1437 @@
+use std::{
+    cell::Cell,
+    convert::Infallible,
+    fmt,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use glow::HasContext;
+use glutin::{context::ContextApi, error::ErrorKind};
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle, RawWindowHandle};
+use winit::{
+    dpi::PhysicalPosition,
+    error::OsError,
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder},
+};
+
+use crate::{
+    caps::GlCaps,
+    context::{
+        create_shared_contexts, drain_gl_errors, ContextId, ContextOptions, ContextWrapper,
+        CreateError, GlVersionRequest, ObjectError, SharingDiagnostic,
+    },
+    diagnostics,
+    display::{
+        create_surface_with_color_space, select_display_config, ColorDepth, DisplayConfig,
+        EffectiveConfig, PickError, SurfaceColorSpace,
+    },
+    monitor::{fullscreen, fullscreen_size, window_position, MonitorSelector},
+    present::{
+        blit_to_window, AlphaMode, AlphaPass, AspectMode, BlitError, QuadPresenter, ShareObject,
+        SourceTexture,
+    },
+    rect::Rect,
+    surface::{create_headless_surface, HeadlessPresent, HeadlessSurface},
+    target::{LocalTarget, MsaaConfig, RenderTargetSizing, SharedTarget},
+    timing::{FrameClock, FrameTimers, GpuTimes},
+};
+
+type WindowEventFn = dyn FnMut(&WindowEvent<'_>) -> bool;
+type RedrawFn = dyn FnMut(&mut Frame<'_>) -> Result<(), FrameError>;
+
+/// Why [`AppBuilder::run`] could not start.
+#[derive(Debug)]
+pub enum AppError {
+    Window(OsError),
+    Pick(PickError),
+    Create(CreateError),
+    Context(glutin::error::Error),
+    Gl(String),
+    /// None of the monitors [`span_monitors`](crate::wall::span_monitors)
+    /// was asked to span exist.
+    NoMonitor,
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Window(err) => write!(f, "failed to create window: {}", err),
+            AppError::Pick(err) => write!(f, "failed to pick config: {}", err),
+            AppError::Create(err) => write!(f, "{}", err),
+            AppError::Context(err) => write!(f, "context error: {}", err),
+            AppError::Gl(err) => write!(f, "gl error: {}", err),
+            AppError::NoMonitor => write!(f, "no monitor of the layout exists"),
+        }
+    }
+}
+
+impl From<OsError> for AppError {
+    fn from(err: OsError) -> Self {
+        AppError::Window(err)
+    }
+}
+
+impl From<PickError> for AppError {
+    fn from(err: PickError) -> Self {
+        AppError::Pick(err)
+    }
+}
+
+impl From<CreateError> for AppError {
+    fn from(err: CreateError) -> Self {
+        AppError::Create(err)
+    }
+}
+
+impl From<glutin::error::Error> for AppError {
+    fn from(err: glutin::error::Error) -> Self {
+        AppError::Context(err)
+    }
+}
+
+impl From<ObjectError> for AppError {
+    fn from(err: ObjectError) -> Self {
+        match err {
+            ObjectError::Context(err) => AppError::Context(err),
+            ObjectError::Gl(err) => AppError::Gl(err),
+        }
+    }
+}
+
+/// Why a step of a [`Frame`] failed.
+#[derive(Debug)]
+pub enum FrameError {
+    /// A make current or present failed. The app recovers if the contexts
+    /// were lost with it.
+    Context(glutin::error::Error),
+    /// Nothing was copied to the window, the frame is skipped.
+    Blit(BlitError),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::Context(err) => write!(f, "context error: {}", err),
+            FrameError::Blit(err) => write!(f, "frame not presented: {}", err),
+        }
+    }
+}
+
+impl From<glutin::error::Error> for FrameError {
+    fn from(err: glutin::error::Error) -> Self {
+        FrameError::Context(err)
+    }
+}
+
+impl From<BlitError> for FrameError {
+    fn from(err: BlitError) -> Self {
+        FrameError::Blit(err)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Render in the headless context into a shared target, then present it in
+    /// the window context.
+    #[default]
+    Offscreen,
+    /// Render in the window context straight to its default framebuffer. No
+    /// shared target is allocated and nothing is blitted.
+    Direct,
+    /// Render in the window context into a [`LocalTarget`] of its own, then
+    /// blit it to the default framebuffer. Nothing is shared and the headless
+    /// context is never used, so this keeps an offscreen buffer, e.g. for
+    /// post-processing, on drivers where sharing is broken.
+    OffscreenSingleContext,
+}
+
+/// When the app applies a window resize to its surfaces and targets.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResizeTiming {
+    /// On every `Resized` event.
+    #[default]
+    Immediate,
+    /// At the start of the next redraw, with the last size received until
+    /// then, which fights the compositor less on some platforms.
+    OnRedraw,
+}
+
+/// Switches a running app between [`RenderMode`]s, e.g. from a key press in
+/// [`AppBuilder::on_window_event`], to tell whether a visual bug is in the
+/// sharing and presentation or in the scene. Clones control the same app.
+#[derive(Clone, Debug)]
+pub struct RenderModeToggle(Rc<Cell<RenderMode>>);
+
+impl RenderModeToggle {
+    /// Flips between offscreen and direct rendering from the next event on,
+    /// returning the new mode.
+    pub fn toggle_render_mode(&self) -> RenderMode {
+        let mode = match self.0.get() {
+            RenderMode::Offscreen | RenderMode::OffscreenSingleContext => RenderMode::Direct,
+            RenderMode::Direct => RenderMode::Offscreen,
+        };
+        self.0.set(mode);
+        mode
+    }
+
+    pub fn set(&self, mode: RenderMode) {
+        self.0.set(mode);
+    }
+
+    pub fn get(&self) -> RenderMode {
+        self.0.get()
+    }
+}
+
+/// Sets up a window with a shared headless context and runs the event loop,
+/// rendering through the headless context and blitting to the window.
+pub struct AppBuilder {
+    fullscreen: Option<MonitorSelector>,
+    monitor: Option<MonitorSelector>,
+    position: Option<PhysicalPosition<i32>>,
+    color_depth: ColorDepth,
+    samples: u8,
+    depth_stencil: Option<(u8, u8)>,
+    headless_surface: HeadlessSurface,
+    headless_present: Option<HeadlessPresent>,
+    color_space: SurfaceColorSpace,
+    share_object: ShareObject,
+    render_mode: RenderMode,
+    offscreen_srgb: bool,
+    present_srgb: bool,
+    alpha_mode: AlphaMode,
+    msaa: Option<MsaaConfig>,
+    sizing: RenderTargetSizing,
+    toggle: Option<RenderModeToggle>,
+    clock: Option<FrameClock>,
+    context_options: ContextOptions,
+    resize_debounce: Option<Duration>,
+    resize_timing: ResizeTiming,
+    on_window_event: Option<Box<WindowEventFn>>,
+    on_gpu_times: Option<Box<dyn FnMut(GpuTimes)>>,
+    on_recover: Option<Box<dyn FnMut(&glow::Context)>>,
+    on_redraw: Option<Box<RedrawFn>>,
+    on_configured: Option<Box<dyn FnOnce(&EffectiveConfig)>>,
+    on_sharing_diagnostic: Option<Box<dyn FnOnce(&SharingDiagnostic)>>,
+    check_callbacks: bool,
+    pause_on_unfocus: bool,
+}
+
+impl Default for AppBuilder {
+    fn default() -> Self {
+        Self {
+            fullscreen: None,
+            monitor: None,
+            position: None,
+            color_depth: ColorDepth::Srgb8,
+            samples: 0,
+            depth_stencil: Some((0, 0)),
+            headless_surface: HeadlessSurface::Auto,
+            headless_present: None,
+            color_space: SurfaceColorSpace::Default,
+            share_object: ShareObject::Texture,
+            render_mode: RenderMode::Offscreen,
+            offscreen_srgb: false,
+            present_srgb: false,
+            alpha_mode: AlphaMode::Ignore,
+            msaa: None,
+            sizing: RenderTargetSizing::Exact,
+            toggle: None,
+            clock: None,
+            context_options: ContextOptions::default(),
+            resize_debounce: None,
+            resize_timing: ResizeTiming::Immediate,
+            on_window_event: None,
+            on_gpu_times: None,
+            on_recover: None,
+            on_redraw: None,
+            on_configured: None,
+            on_sharing_diagnostic: None,
+            check_callbacks: true,
+            pause_on_unfocus: false,
+        }
+    }
+}
+
+impl AppBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the window fullscreen on the selected monitor, sized to its
+    /// video mode.
+    pub fn fullscreen(mut self, selector: Option<MonitorSelector>) -> Self {
+        self.fullscreen = selector;
+        self
+    }
+
+    /// Places the regular window at `position`, in desktop coordinates or
+    /// relative to the monitor set with [`Self::monitor`]. Ignored when
+    /// fullscreen.
+    pub fn window_position(mut self, position: Option<PhysicalPosition<i32>>) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Opens the regular window on the selected monitor, at its top left
+    /// corner unless [`Self::window_position`] is set.
+    pub fn monitor(mut self, selector: Option<MonitorSelector>) -> Self {
+        self.monitor = selector;
+        self
+    }
+
+    pub fn color_depth(mut self, color_depth: ColorDepth) -> Self {
+        self.color_depth = color_depth;
+        self
+    }
+
+    pub fn samples(mut self, samples: u8) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// See [`select_display_config`], defaults to `Some((0, 0))` since the
+    /// window only ever receives blits.
+    pub fn depth_stencil(mut self, depth_stencil: Option<(u8, u8)>) -> Self {
+        self.depth_stencil = depth_stencil;
+        self
+    }
+
+    pub fn headless_surface(mut self, headless_surface: HeadlessSurface) -> Self {
+        self.headless_surface = headless_surface;
+        self
+    }
+
+    /// What finishing a frame does with the headless surface, `None` for the
+    /// default of its kind, see [`ContextWrapper::set_headless_present`].
+    pub fn headless_present(mut self, present: Option<HeadlessPresent>) -> Self {
+        self.headless_present = present;
+        self
+    }
+
+    /// Tags the window surface for color managed compositors, falling back to
+    /// the default where unsupported, see [`create_surface_with_color_space`].
+    pub fn surface_color_space(mut self, color_space: SurfaceColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    pub fn share_object(mut self, share_object: ShareObject) -> Self {
+        self.share_object = share_object;
+        self
+    }
+
+    pub fn render_mode(mut self, render_mode: RenderMode) -> Self {
+        self.render_mode = render_mode;
+        self
+    }
+
+    /// A handle switching the render mode while the app runs. The app starts
+    /// in [`Self::render_mode`] either way, but keeps the shared target of
+    /// [`RenderMode::Offscreen`] around even while rendering directly, and
+    /// the render callback draws to whichever target is active.
+    pub fn render_mode_toggle(&mut self) -> RenderModeToggle {
+        self.toggle
+            .get_or_insert_with(|| RenderModeToggle(Rc::new(Cell::new(self.render_mode))))
+            .clone()
+    }
+
+    /// A clock the app advances right before rendering each frame, for the
+    /// render callback to animate by frame index or a fixed timestep.
+    pub fn frame_clock(&mut self) -> FrameClock {
+        self.clock.get_or_insert_with(FrameClock::new).clone()
+    }
+
+    /// Requests OpenGL or OpenGL ES contexts, the driver picks if `None`.
+    pub fn context_api(mut self, api: Option<ContextApi>) -> Self {
+        self.context_options.api = api;
+        self
+    }
+
+    /// Requests forward-compatible core profile contexts, see
+    /// [`ContextOptions::forward_compatible`]. A warning is printed if the
+    /// window context does not end up forward compatible.
+    pub fn forward_compatible(mut self, forward_compatible: bool) -> Self {
+        self.context_options.forward_compatible = forward_compatible;
+        self
+    }
+
+    /// Requests debug contexts, see [`ContextOptions::debug`].
+    pub fn debug_context(mut self, debug: bool) -> Self {
+        self.context_options.debug = debug;
+        self
+    }
+
+    /// Requests no-error contexts, see [`ContextOptions::no_error`]. Setup
+    /// fails with [`CreateError::ConflictingFlags`] if combined with
+    /// [`Self::debug_context`] or [`Self::robust`].
+    pub fn no_error(mut self, no_error: bool) -> Self {
+        self.context_options.no_error = no_error;
+        self
+    }
+
+    /// Requests robust contexts, see [`ContextOptions::robust`].
+    pub fn robust(mut self, robust: bool) -> Self {
+        self.context_options.robust = robust;
+        self
+    }
+
+    /// Which GL version to create the contexts with, see [`GlVersionRequest`].
+    pub fn gl_version(mut self, version: GlVersionRequest) -> Self {
+        self.context_options.version = version;
+        self
+    }
+
+    /// Stores the shared target sRGB encoded, so rendering into it encodes
+    /// and sampling it decodes. Only applies to [`ColorDepth::Srgb8`], deeper
+    /// formats are kept linear. Off by default, which keeps the target linear
+    /// for blending.
+    pub fn offscreen_srgb(mut self, srgb: bool) -> Self {
+        self.offscreen_srgb = srgb;
+        self
+    }
+
+    /// Encodes the presented image to sRGB on the way to the window.
+    /// `glBlitFramebuffer` cannot convert, so this presents through a shared
+    /// texture and [`QuadPresenter::new_srgb`], whatever
+    /// [`Self::share_object`] says.
+    pub fn present_srgb(mut self, srgb: bool) -> Self {
+        self.present_srgb = srgb;
+        self
+    }
+
+    /// How the alpha of the rendered image reaches the compositor. Anything
+    /// but [`AlphaMode::Ignore`] asks for a transparent window, and
+    /// [`AlphaMode::Premultiplied`] runs an [`AlphaPass`] over the window
+    /// after every blit.
+    pub fn alpha_mode(mut self, mode: AlphaMode) -> Self {
+        self.alpha_mode = mode;
+        self
+    }
+
+    /// Renders multisampled in [`RenderMode::Offscreen`] and resolves into the
+    /// shared target every frame, see [`SharedTarget::set_msaa`]. Independent
+    /// of [`Self::samples`], which multisamples the window config.
+    pub fn offscreen_msaa(mut self, msaa: Option<MsaaConfig>) -> Self {
+        self.msaa = msaa;
+        self
+    }
+
+    /// Allocates the shared target once at a fixed size instead of on every
+    /// resize, see [`SharedTarget::set_sizing`].
+    pub fn render_target_sizing(mut self, sizing: RenderTargetSizing) -> Self {
+        self.sizing = sizing;
+        self
+    }
+
+    /// Reallocates the shared target only once no resize happened for `delay`,
+    /// stretching the old one to the window meanwhile. The window surface
+    /// still follows every resize immediately.
+    pub fn resize_debounce(mut self, delay: Option<Duration>) -> Self {
+        self.resize_debounce = delay;
+        self
+    }
+
+    pub fn resize_timing(mut self, timing: ResizeTiming) -> Self {
+        self.resize_timing = timing;
+        self
+    }
+
+    /// Forwards every window event to `f` before the app handles it. Returning
+    /// `true` suppresses the default handling of resizes and close requests.
+    pub fn on_window_event(mut self, f: impl FnMut(&WindowEvent<'_>) -> bool + 'static) -> Self {
+        self.on_window_event = Some(Box::new(f));
+        self
+    }
+
+    /// Measures the render and blit with timer queries and reports the
+    /// results, usually a frame late, after each presented frame. Only
+    /// [`RenderMode::Offscreen`] reports times.
+    pub fn on_gpu_times(mut self, f: impl FnMut(GpuTimes) + 'static) -> Self {
+        self.on_gpu_times = Some(Box::new(f));
+        self
+    }
+
+    /// Reports GL errors left behind by `render` and [`Self::on_recover`] on
+    /// stderr, naming the callback. Pending errors are drained before every
+    /// callback either way, checking only happens in debug builds.
+    pub fn debug_check_callbacks(mut self, check: bool) -> Self {
+        self.check_callbacks = check;
+        self
+    }
+
+    /// Stops rendering while the window is unfocused. Redraws are skipped and
+    /// nothing is swapped until focus returns, resizes in between are applied
+    /// then.
+    pub fn pause_on_unfocus(mut self, pause: bool) -> Self {
+        self.pause_on_unfocus = pause;
+        self
+    }
+
+    /// Called after a lost context was replaced and the shared target
+    /// reallocated, with the new headless context current, to recreate GL
+    /// objects the render callback relies on.
+    pub fn on_recover(mut self, f: impl FnMut(&glow::Context) + 'static) -> Self {
+        self.on_recover = Some(Box::new(f));
+        self
+    }
+
+    /// Replaces the default redraw, which runs [`Frame::render_offscreen`],
+    /// [`Frame::blit_to_window`] and [`Frame::swap`] in that order, so steps
+    /// can be inserted between them or skipped. Errors are handled like
+    /// those of the default redraw.
+    pub fn on_redraw(
+        mut self,
+        f: impl FnMut(&mut Frame<'_>) -> Result<(), FrameError> + 'static,
+    ) -> Self {
+        self.on_redraw = Some(Box::new(f));
+        self
+    }
+
+    /// Called once when setup is complete, before the first frame, with the
+    /// backend, config, GL version and renderer the app actually got.
+    pub fn on_configured(mut self, f: impl FnOnce(&EffectiveConfig) + 'static) -> Self {
+        self.on_configured = Some(Box::new(f));
+        self
+    }
+
+    /// Called during setup if [`ContextWrapper::validate_sharing`] finds the
+    /// contexts unlikely to share objects. Not called when rendering with a
+    /// single context, which does not share anything.
+    pub fn on_sharing_diagnostic(mut self, f: impl FnOnce(&SharingDiagnostic) + 'static) -> Self {
+        self.on_sharing_diagnostic = Some(Box::new(f));
+        self
+    }
+
+    /// Runs the event loop, calling `render` whenever the window needs to be
+    /// redrawn, with the shared target bound in the headless context or, in
+    /// [`RenderMode::Direct`], the default framebuffer bound in the window
+    /// context.
+    ///
+    /// Only returns if setting up the window and contexts fails. Errors once
+    /// the loop runs are printed and end the loop.
+    pub fn run<T: 'static>(
+        self,
+        event_loop: EventLoop<T>,
+        render: impl FnMut(&glow::Context) + 'static,
+    ) -> Result<Infallible, AppError> {
+        self.run_with_user_events(event_loop, render, |_| true)
+    }
+
+    /// Like [`Self::run`], but passes user events sent through an
+    /// `EventLoopProxy` to `on_user_event` and renders and presents a frame
+    /// whenever it returns `true`, so other threads can drive the rendering:
+    ///
+    /// ```ignore
+    /// let event_loop = EventLoopBuilder::with_user_event().build();
+    /// let proxy = event_loop.create_proxy();
+    /// std::thread::spawn(move || loop {
+    ///     let _ = proxy.send_event(UserEvent::Render);
+    ///     std::thread::sleep(Duration::from_millis(100));
+    /// });
+    /// AppBuilder::new().run_with_user_events(event_loop, render, |event| {
+    ///     matches!(event, UserEvent::Render)
+    /// })
+    /// ```
+    pub fn run_with_user_events<T: 'static>(
+        self,
+        event_loop: EventLoop<T>,
+        mut render: impl FnMut(&glow::Context) + 'static,
+        mut on_user_event: impl FnMut(T) -> bool + 'static,
+    ) -> Result<Infallible, AppError> {
+        let mut runner = self.setup(&event_loop)?;
+        event_loop.run(move |event, _, cf| {
+            if let Event::LoopDestroyed = event {
+                // winit exits the process without dropping the closure, so tear down explicitly
+                runner.shutdown();
+                return;
+            }
+            runner.handle(event, cf, &mut render, &mut on_user_event);
+        })
+    }
+
+    /// Sets up the window and contexts like [`Self::run`], but leaves running
+    /// the event loop to the caller through [`PumpedApp::run_pumped`].
+    #[cfg(feature = "pump")]
+    pub fn build_pumped(self, event_loop: EventLoop<()>) -> Result<PumpedApp, AppError> {
+        let runner = self.setup(&event_loop)?;
+        Ok(PumpedApp { event_loop, runner })
+    }
+
+    fn setup<T>(mut self, event_loop: &EventLoop<T>) -> Result<Runner, AppError> {
+        let raw_display = event_loop.raw_display_handle();
+        let fullscreen = fullscreen(event_loop, self.fullscreen.take());
+        let mut window = WindowBuilder::new()
+            .with_fullscreen(fullscreen.clone())
+            .with_transparent(self.alpha_mode != AlphaMode::Ignore);
+        if let Some(position) = window_position(event_loop, self.monitor.take(), self.position) {
+            window = window.with_position(position);
+        }
+        let window = window.build(event_loop)?;
+        let raw_wnd = window.raw_window_handle();
+
+        let DisplayConfig {
+            display,
+            config,
+            color_depth,
+            ..
+        } = select_display_config(
+            raw_display,
+            raw_wnd,
+            self.color_depth,
+            self.samples,
+            self.depth_stencil,
+        )?;
+
+        // the window may not report the fullscreen size until the mode switch happened
+        let size = fullscreen
+            .as_ref()
+            .and_then(fullscreen_size)
+            .unwrap_or_else(|| window.inner_size());
+
+        let mut ctx = {
+            let (window_surface, _) = create_surface_with_color_space(
+                size.width,
+                size.height,
+                &display,
+                &config,
+                raw_wnd,
+                self.color_space,
+            )?;
+            let headless_surface =
+                create_headless_surface(&display, &config, raw_wnd, self.headless_surface)?;
+
+            let (windowed_context, headless_context) = create_shared_contexts(
+                &display,
+                &config,
+                None,
+                raw_wnd,
+                &headless_surface,
+                self.context_options,
+            )?;
+
+            ContextWrapper::new(
+                display,
+                window_surface,
+                headless_surface,
+                windowed_context,
+                headless_context,
+            )?
+        };
+        ctx.set_headless_present(self.headless_present);
+
+        // a single context mode does not care whether sharing works
+        if let Some(f) = self.on_sharing_diagnostic.take() {
+            if self.render_mode != RenderMode::OffscreenSingleContext {
+                if let Err(diag) = ctx.validate_sharing() {
+                    f(&diag);
+                }
+            }
+        }
+
+        let settings = Settings {
+            raw_wnd,
+            context_options: self.context_options,
+            mode: self.render_mode,
+            share_object: self.share_object,
+            format: match (self.offscreen_srgb, color_depth) {
+                (true, ColorDepth::Srgb8) => glow::SRGB8_ALPHA8,
+                _ => color_depth.renderbuffer_format(),
+            },
+            present_srgb: self.present_srgb,
+            alpha_mode: self.alpha_mode,
+            msaa: self.msaa,
+            sizing: self.sizing,
+            toggle: self.toggle.take().map(|toggle| {
+                toggle.set(self.render_mode);
+                toggle
+            }),
+            clock: self.clock.take(),
+            gpu_timing: self.on_gpu_times.is_some(),
+            resize_debounce: self.resize_debounce,
+            resize_timing: self.resize_timing,
+            check_callbacks: self.check_callbacks && cfg!(debug_assertions),
+        };
+        let mut app = App::new(ctx, settings, (size.width, size.height))?;
+        let format = app
+            .target
+            .as_ref()
+            .map_or(app.settings.format, SharedTarget::format);
+        let config = app.ctx.effective_config(format)?;
+        diagnostics::record_config(&config);
+        if let Some(f) = self.on_configured.take() {
+            f(&config);
+        }
+
+        Ok(Runner {
+            window,
+            app: Some(app),
+            paused: None,
+            pause_on_unfocus: self.pause_on_unfocus,
+            on_window_event: self.on_window_event,
+            on_gpu_times: self.on_gpu_times,
+            on_recover: self.on_recover,
+            on_redraw: self.on_redraw,
+        })
+    }
+}
+
+/// The frame being redrawn, handed to [`AppBuilder::on_redraw`]. Each step
+/// makes the context it needs current and leaves none current.
+pub struct Frame<'a> {
+    app: &'a mut App,
+    render: &'a mut dyn FnMut(&glow::Context),
+}
+
+impl Frame<'_> {
+    /// Runs the render callback of [`AppBuilder::run`] on the frame. In
+    /// [`RenderMode::Offscreen`] it makes the headless context current,
+    /// renders into the shared target and presents the headless surface so
+    /// the window context sees the frame. The other modes make the window
+    /// context current and render into the local target or the window.
+    pub fn render_offscreen(&mut self) -> Result<(), FrameError> {
+        self.app
+            .render_offscreen(self.render)
+            .map_err(FrameError::Context)
+    }
+
+    /// Makes the window context current and copies the frame of
+    /// [`Self::render_offscreen`] to the window, which draws nothing in
+    /// [`RenderMode::Direct`].
+    pub fn blit_to_window(&mut self) -> Result<(), FrameError> {
+        self.app.blit_to_window()
+    }
+
+    /// Makes the window context current and presents the window surface.
+    pub fn swap(&mut self) -> Result<(), FrameError> {
+        self.app.swap().map_err(FrameError::Context)
+    }
+
+    /// The render mode of the frame, which a [`RenderModeToggle`] may change
+    /// between frames.
+    pub fn mode(&self) -> RenderMode {
+        self.app.drawn_mode
+    }
+}
+
+/// An app whose event loop is driven by the caller, see
+/// [`AppBuilder::build_pumped`].
+#[cfg(feature = "pump")]
+pub struct PumpedApp {
+    event_loop: EventLoop<()>,
+    runner: Runner,
+}
+
+/// Whether [`PumpedApp::run_pumped`] wants to be called again.
+#[cfg(feature = "pump")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PumpStatus {
+    Continue,
+    /// The window was closed or an error ended the loop, the app is shut down.
+    Exit,
+}
+
+#[cfg(feature = "pump")]
+impl PumpedApp {
+    /// Handles the pending events, including resizes and redraws, and returns
+    /// without waiting for new ones. `render` is called as in
+    /// [`AppBuilder::run`].
+    ///
+    /// winit before 0.29 has no `pump_events`, so this runs the loop with
+    /// `run_return` until the events of one iteration are handled, which is
+    /// only available on desktop platforms.
+    pub fn run_pumped(&mut self, mut render: impl FnMut(&glow::Context)) -> PumpStatus {
+        use winit::platform::run_return::EventLoopExtRunReturn;
+
+        if self.runner.app.is_none() {
+            return PumpStatus::Exit;
+        }
+        let runner = &mut self.runner;
+        let mut exit = false;
+        self.event_loop.run_return(|event, _, cf| {
+            match event {
+                // sent for every return, not only at the end of the app
+                Event::LoopDestroyed => return,
+                Event::RedrawEventsCleared => {
+                    *cf = ControlFlow::Exit;
+                    return;
+                }
+                _ => {}
+            }
+            runner.handle(event, cf, &mut render, &mut |()| false);
+            if *cf == ControlFlow::Exit {
+                exit = true;
+            } else {
+                *cf = ControlFlow::Poll;
+            }
+        });
+        if exit {
+            self.runner.shutdown();
+            return PumpStatus::Exit;
+        }
+        PumpStatus::Continue
+    }
+
+    fn app(&mut self) -> glutin::error::Result<&mut App> {
+        self.runner
+            .app
+            .as_mut()
+            .ok_or_else(|| ErrorKind::BadContextState.into())
+    }
+
+    /// Starts a frame outside of [`Self::run_pumped`] and runs `render` on
+    /// it, for inserting own steps before [`Self::blit_to_window`] and
+    /// [`Self::swap`]. In [`RenderMode::Offscreen`] it makes the headless
+    /// context current, renders into the shared target and presents the
+    /// headless surface so the window context sees the frame. The other
+    /// modes make the window context current and render into the local
+    /// target or the window.
+    pub fn render_offscreen(
+        &mut self,
+        mut render: impl FnMut(&glow::Context),
+    ) -> glutin::error::Result<()> {
+        self.app()?.render_offscreen(&mut render)
+    }
+
+    /// Makes the window context current and copies the frame of
+    /// [`Self::render_offscreen`] to the window, which draws nothing in
+    /// [`RenderMode::Direct`].
+    pub fn blit_to_window(&mut self) -> Result<(), FrameError> {
+        self.app()?.blit_to_window()
+    }
+
+    /// Makes the window context current and presents the window surface.
+    pub fn swap(&mut self) -> glutin::error::Result<()> {
+        self.app()?.swap()
+    }
+}
+
+#[cfg(feature = "pump")]
+impl Drop for PumpedApp {
+    fn drop(&mut self) {
+        self.runner.shutdown();
+    }
+}
+
+/// The event handling shared by [`AppBuilder::run`] and
+/// [`PumpedApp::run_pumped`].
+struct Runner {
+    window: Window,
+    app: Option<App>,
+    /// The size of the last resize while paused, applied on resume.
+    paused: Option<Option<(u32, u32)>>,
+    pause_on_unfocus: bool,
+    on_window_event: Option<Box<WindowEventFn>>,
+    on_gpu_times: Option<Box<dyn FnMut(GpuTimes)>>,
+    on_recover: Option<Box<dyn FnMut(&glow::Context)>>,
+    on_redraw: Option<Box<RedrawFn>>,
+}
+
+impl Runner {
+    fn handle<T>(
+        &mut self,
+        event: Event<'_, T>,
+        cf: &mut ControlFlow,
+        render: &mut dyn FnMut(&glow::Context),
+        on_user_event: &mut dyn FnMut(T) -> bool,
+    ) {
+        *cf = ControlFlow::Wait;
+
+        let app = match self.app.as_mut() {
+            Some(app) => app,
+            None => return,
+        };
+        let window = &self.window;
+        if app.mode() != app.drawn_mode {
+            window.request_redraw();
+        }
+        let paused = &mut self.paused;
+        match app.pending_resize().filter(|_| paused.is_none()) {
+            Some(deadline) if Instant::now() >= deadline => {
+                if let Err(err) = app.resize_target() {
+                    return recover_or_fail(app, &mut self.on_recover, window, cf, "resize", err);
+                }
+                window.request_redraw();
+            }
+            Some(deadline) => *cf = ControlFlow::WaitUntil(deadline),
+            None => {}
+        }
+
+        match event {
+            Event::WindowEvent { event, .. } => {
+                if let Some(f) = &mut self.on_window_event {
+                    if f(&event) {
+                        return;
+                    }
+                }
+                match event {
+                    WindowEvent::Resized(size) if paused.is_some() => {
+                        *paused = Some(Some((size.width, size.height)));
+                    }
+                    WindowEvent::Resized(size) => {
+                        if let Err(err) = app.on_resized(size.width, size.height) {
+                            let on_recover = &mut self.on_recover;
+                            return recover_or_fail(app, on_recover, window, cf, "resize", err);
+                        }
+                        if let Some(deadline) = app.pending_resize() {
+                            *cf = ControlFlow::WaitUntil(deadline);
+                        }
+                        window.request_redraw();
+                    }
+                    WindowEvent::Focused(false) if self.pause_on_unfocus => {
+                        paused.get_or_insert(None);
+                    }
+                    WindowEvent::Focused(true) => {
+                        if let Some(Some((width, height))) = paused.take() {
+                            if let Err(err) = app.on_resized(width, height) {
+                                let on_recover = &mut self.on_recover;
+                                return recover_or_fail(app, on_recover, window, cf, "resize", err);
+                            }
+                            if let Some(deadline) = app.pending_resize() {
+                                *cf = ControlFlow::WaitUntil(deadline);
+                            }
+                        }
+                        window.request_redraw();
+                    }
+                    WindowEvent::CloseRequested => *cf = ControlFlow::Exit,
+                    _ => {}
+                }
+            }
+            Event::UserEvent(event) => {
+                if on_user_event(event) {
+                    window.request_redraw();
+                }
+            }
+            Event::RedrawRequested(_) if paused.is_some() => {}
+            Event::RedrawRequested(_) => {
+                if let Some((width, height)) = app.deferred_size.take() {
+                    if let Err(err) = app.resize(width, height) {
+                        return recover_or_fail(
+                            app,
+                            &mut self.on_recover,
+                            window,
+                            cf,
+                            "resize",
+                            err,
+                        );
+                    }
+                }
+                let drawn = match &mut self.on_redraw {
+                    Some(f) => f(&mut Frame {
+                        app: &mut *app,
+                        render: &mut *render,
+                    }),
+                    None => app.redraw(render),
+                };
+                match drawn {
+                    Ok(()) => {}
+                    // e.g. a minimized window, the next frame may fit again
+                    Err(FrameError::Blit(err)) => eprintln!("frame not presented: {}", err),
+                    Err(FrameError::Context(err)) => {
+                        let on_recover = &mut self.on_recover;
+                        return recover_or_fail(app, on_recover, window, cf, "present", err);
+                    }
+                }
+                if let (Some(times), Some(f)) = (app.last_gpu_times(), &mut self.on_gpu_times) {
+                    f(times);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn shutdown(&mut self) {
+        if let Some(app) = self.app.take() {
+            app.shutdown();
+        }
+    }
+}
+
+/// Settings the app needs again when it rebuilds its GL state.
+struct Settings {
+    raw_wnd: RawWindowHandle,
+    context_options: ContextOptions,
+    mode: RenderMode,
+    share_object: ShareObject,
+    format: u32,
+    present_srgb: bool,
+    alpha_mode: AlphaMode,
+    msaa: Option<MsaaConfig>,
+    sizing: RenderTargetSizing,
+    toggle: Option<RenderModeToggle>,
+    clock: Option<FrameClock>,
+    gpu_timing: bool,
+    resize_debounce: Option<Duration>,
+    resize_timing: ResizeTiming,
+    check_callbacks: bool,
+}
+
+impl Settings {
+    /// Whether the shared target is needed, now or after a toggle.
+    fn builds_target(&self) -> bool {
+        self.mode == RenderMode::Offscreen || self.toggle.is_some()
+    }
+}
+
+/// State of a running app, owned by the event loop closure.
+struct App {
+    ctx: ContextWrapper,
+    settings: Settings,
+    glw: Rc<glow::Context>,
+    glh: Rc<glow::Context>,
+    target: Option<SharedTarget>,
+    local: Option<LocalTarget>,
+    presenter: Option<QuadPresenter>,
+    alpha: Option<AlphaPass>,
+    timers: Option<FrameTimers>,
+    width: u32,
+    height: u32,
+    resize_deadline: Option<Instant>,
+    /// Size of the last resize waiting for the next redraw.
+    deferred_size: Option<(u32, u32)>,
+    /// Mode of the last frame, to redraw when a toggle changes it.
+    drawn_mode: RenderMode,
+}
+
+impl App {
+    fn new(
+        ctx: ContextWrapper,
+        settings: Settings,
+        (width, height): (u32, u32),
+    ) -> Result<Self, AppError> {
+        let mut app = Self {
+            glw: ctx.gl_handle(ContextId::Window),
+            glh: ctx.gl_handle(ContextId::Headless),
+            drawn_mode: settings.mode,
+            ctx,
+            settings,
+            target: None,
+            local: None,
+            presenter: None,
+            alpha: None,
+            timers: None,
+            width,
+            height,
+            resize_deadline: None,
+            deferred_size: None,
+        };
+        app.build()?;
+        Ok(app)
+    }
+
+    /// Creates the target, presenter and timers for the current contexts.
+    fn build(&mut self) -> Result<(), AppError> {
+        let ctx = &mut self.ctx;
+        let settings = &self.settings;
+        let glw = ctx.gl_handle(ContextId::Window);
+        let glh = ctx.gl_handle(ContextId::Headless);
+        let (width, height) = (self.width, self.height);
+
+        let c = ctx.try_ct_wnd()?;
+        let caps_w = GlCaps::query(&glw);
+        ctx.put_wnd(c)?;
+        if settings.context_options.forward_compatible && !caps_w.forward_compatible() {
+            eprintln!("requested a forward-compatible context, but got a compatible one");
+        }
+
+        let c = ctx.try_ct_head()?;
+        let caps_h = GlCaps::query(&glh);
+        ctx.put_head(c)?;
+
+        // without glBlitFramebuffer the window can only sample a texture
+        let mut share_object = if caps_w.has_blit() && !settings.present_srgb {
+            settings.share_object
+        } else {
+            ShareObject::Texture
+        };
+        if settings.builds_target() {
+            let mut sharing = ctx.probe_sharing_capabilities()?;
+            sharing.renderbuffer &= caps_w.has_blit();
+            match sharing.share_object(share_object) {
+                Some(shared) => share_object = shared,
+                None => eprintln!("neither textures nor renderbuffers are shared"),
+            }
+            if settings.present_srgb && share_object != ShareObject::Texture {
+                eprintln!("textures are not shared, presenting without sRGB encoding");
+            }
+        }
+        if settings.format == glow::SRGB8_ALPHA8 && !caps_h.is_gles() {
+            // desktop GL only encodes into sRGB attachments with this enabled
+            let c = ctx.try_ct_head()?;
+            unsafe { glh.enable(glow::FRAMEBUFFER_SRGB) };
+            ctx.put_head(c)?;
+        }
+
+        let format = settings.format;
+        let (target, presenter) = if settings.builds_target() {
+            let mut target = match share_object {
+                ShareObject::Renderbuffer => {
+                    SharedTarget::new(ctx, &glw, &glh, format, width, height)
+                }
+                ShareObject::Texture => {
+                    SharedTarget::new_texture(ctx, &glw, &glh, format, width, height)
+                }
+            }?;
+            target.set_sizing(ctx, &glw, settings.sizing)?;
+            if settings.msaa.is_some() {
+                target.set_msaa(ctx, &glw, &glh, &caps_h, settings.msaa)?;
+            }
+            let presenter = match share_object {
+                ShareObject::Renderbuffer => None,
+                ShareObject::Texture => {
+                    let c = ctx.try_ct_wnd()?;
+                    let presenter = if settings.present_srgb {
+                        QuadPresenter::new_srgb(&glw, &caps_w)
+                    } else {
+                        QuadPresenter::new(&glw, &caps_w)
+                    };
+                    ctx.put_wnd(c)?;
+                    Some(presenter.map_err(AppError::Gl)?)
+                }
+            };
+            (Some(target), presenter)
+        } else {
+            (None, None)
+        };
+
+        // only premultiplying needs a pass of its own
+        let alpha = if settings.alpha_mode == AlphaMode::Premultiplied {
+            let c = ctx.try_ct_wnd()?;
+            let alpha = AlphaPass::new(&glw, &caps_w, settings.alpha_mode);
+            ctx.put_wnd(c)?;
+            Some(alpha.map_err(AppError::Gl)?)
+        } else {
+            None
+        };
+
+        let timers = match (settings.gpu_timing, settings.builds_target()) {
+            (true, true) => match FrameTimers::new(ctx, &glw, &glh, &caps_w, &caps_h) {
+                Ok(timers) => Some(timers),
+                Err(err) => {
+                    eprintln!("gpu timing disabled: {}", err);
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        let local = match settings.mode {
+            RenderMode::OffscreenSingleContext => {
+                Some(LocalTarget::new(ctx, &glw, format, width, height)?)
+            }
+            _ => None,
+        };
+
+        self.glw = glw;
+        self.glh = glh;
+        self.target = target;
+        self.local = local;
+        self.presenter = presenter;
+        self.alpha = alpha;
+        self.timers = timers;
+        Ok(())
+    }
+
+    /// Whether `err` left the app without usable contexts: they were lost, or
+    /// a failed make current took one with it.
+    fn needs_recovery(&self, err: &glutin::error::Error) -> bool {
+        err.error_kind() == ErrorKind::ContextLost || !self.ctx.has_contexts()
+    }
+
+    /// Replaces lost contexts and everything created on them, then lets the
+    /// user recreate their own objects with the headless context current.
+    fn recover(
+        &mut self,
+        on_recover: Option<&mut Box<dyn FnMut(&glow::Context)>>,
+    ) -> Result<(), AppError> {
+        // objects of the lost share group are gone with it
+        self.target = None;
+        self.local = None;
+        self.presenter = None;
+        self.alpha = None;
+        self.timers = None;
+        self.resize_deadline = None;
+
+        self.ctx
+            .recover(self.settings.raw_wnd, self.settings.context_options)?;
+        self.build()?;
+
+        if let Some(f) = on_recover {
+            let c = self.ctx.try_ct_head()?;
+            call_checked(&self.glh, "on_recover", self.settings.check_callbacks, f);
+            self.ctx.put_head(c)?;
+        }
+        Ok(())
+    }
+
+    /// Resizes now or on the next redraw, depending on the [`ResizeTiming`].
+    fn on_resized(&mut self, width: u32, height: u32) -> glutin::error::Result<()> {
+        match self.settings.resize_timing {
+            ResizeTiming::Immediate => self.resize(width, height),
+            ResizeTiming::OnRedraw => {
+                self.deferred_size = Some((width, height));
+                Ok(())
+            }
+        }
+    }
+
+    fn resize(&mut self, width: u32, height: u32) -> glutin::error::Result<()> {
+        match self.settings.resize_debounce {
+            Some(delay) if self.target.is_some() => {
+                (self.width, self.height) = self.ctx.resize_window((width, height))?;
+                self.resize_deadline = Some(Instant::now() + delay);
+            }
+            _ => {
+                self.resize_deadline = None;
+                (self.width, self.height) =
+                    self.ctx.resize((width, height), self.target.as_mut())?;
+            }
+        }
+        if let Some(local) = &mut self.local {
+            local.resize(&mut self.ctx, &self.glw, self.width, self.height)?;
+        }
+        Ok(())
+    }
+
+    /// The render mode the next frame uses.
+    fn mode(&self) -> RenderMode {
+        match &self.settings.toggle {
+            Some(toggle) => toggle.get(),
+            None => self.settings.mode,
+        }
+    }
+
+    /// When a debounced target reallocation is due.
+    fn pending_resize(&self) -> Option<Instant> {
+        self.resize_deadline
+    }
+
+    fn resize_target(&mut self) -> glutin::error::Result<()> {
+        self.resize_deadline = None;
+        match &mut self.target {
+            Some(target) => self.ctx.resize_offscreen((self.width, self.height), target),
+            None => Ok(()),
+        }
+    }
+
+    /// Renders and presents a frame with [`Self::render_offscreen`],
+    /// [`Self::blit_to_window`] and [`Self::swap`], skipping the swap if the
+    /// blit failed. Failing with [`ErrorKind::ContextLost`] or on a make
+    /// current calls for [`Self::recover`], see [`Self::needs_recovery`].
+    fn redraw(&mut self, render: &mut dyn FnMut(&glow::Context)) -> Result<(), FrameError> {
+        self.render_offscreen(render)?;
+        self.blit_to_window()?;
+        self.swap().map_err(FrameError::Context)
+    }
+
+    /// Starts a frame in the current render mode and runs `render` on it.
+    /// Offscreen it renders in the headless context into the shared target,
+    /// resolves it and presents the headless surface to sync with the window
+    /// context. Single context offscreen it renders in the window context
+    /// into the local target, directly into the default framebuffer of the
+    /// window context.
+    fn render_offscreen(
+        &mut self,
+        render: &mut dyn FnMut(&glow::Context),
+    ) -> glutin::error::Result<()> {
+        self.drawn_mode = self.mode();
+        if let Some(clock) = &self.settings.clock {
+            clock.tick();
+        }
+        let check = self.settings.check_callbacks;
+        let target = match (&self.target, &self.local, self.drawn_mode) {
+            (Some(target), _, RenderMode::Offscreen) => target,
+            (_, Some(local), RenderMode::OffscreenSingleContext) => {
+                let glw = &*self.glw;
+                let c = self.ctx.try_ct_wnd()?;
+                unsafe { glw.bind_framebuffer(glow::FRAMEBUFFER, Some(local.fb())) };
+                diagnostics::record_framebuffer("window", Some(local.fb()));
+                let (lw, lh) = local.size();
+                Rect::from_size(lw, lh).viewport(glw);
+                unsafe {
+                    glw.clear_color(0.0, 0.0, 0.0, 1.0);
+                    glw.clear(glow::COLOR_BUFFER_BIT);
+                }
+                call_checked(glw, "render", check, render);
+                self.ctx.put_wnd(c)?;
+                return Ok(());
+            }
+            _ => {
+                let c = self.ctx.try_ct_wnd()?;
+                unsafe { self.glw.bind_framebuffer(glow::FRAMEBUFFER, None) };
+                diagnostics::record_framebuffer("window", None);
+                Rect::from_size(self.width, self.height).viewport(&self.glw);
+                call_checked(&self.glw, "render", check, render);
+                self.ctx.put_wnd(c)?;
+                return Ok(());
+            }
+        };
+        let glh = &*self.glh;
+
+        let c = self.ctx.try_ct_head()?;
+        if let Some(timers) = &mut self.timers {
+            timers.render.begin(glh);
+        }
+        unsafe {
+            glh.bind_framebuffer(glow::FRAMEBUFFER, Some(target.render_fb()));
+        }
+        diagnostics::record_framebuffer("headless", Some(target.render_fb()));
+        let (rw, rh) = target.size();
+        Rect::from_size(rw, rh).viewport(glh);
+        target.clear(glh);
+        call_checked(glh, "render", check, render);
+        target.resolve(glh);
+        unsafe {
+            glh.bind_framebuffer(glow::FRAMEBUFFER, None);
+        }
+        if let Some(timers) = &mut self.timers {
+            timers.render.end(glh);
+        }
+        let presented = self.ctx.present(ContextId::Headless, &c);
+        self.ctx.put_head(c)?;
+        presented
+    }
+
+    /// Copies what [`Self::render_offscreen`] rendered offscreen to the
+    /// default framebuffer of the window context, stretched over the window.
+    /// Does nothing when rendering directly.
+    fn blit_to_window(&mut self) -> Result<(), FrameError> {
+        let window_rect = Rect::from_size(self.width, self.height);
+        let bar_color = [0.0, 0.0, 0.0, 1.0];
+        let glw = &*self.glw;
+        let blitted = match (&self.target, &self.local, self.drawn_mode) {
+            (Some(target), _, RenderMode::Offscreen) => {
+                let c = self.ctx.try_ct_wnd()?;
+                if let Some(timers) = &mut self.timers {
+                    timers.blit.begin(glw);
+                }
+                let (rw, rh) = target.size();
+                let src = Rect::from_size(rw, rh);
+                let blitted = match (&self.presenter, target.texture()) {
+                    (Some(presenter), Some(texture)) => presenter.draw(
+                        glw,
+                        SourceTexture {
+                            texture,
+                            size: target.storage_size(),
+                        },
+                        src,
+                        window_rect,
+                        AspectMode::Stretch,
+                        bar_color,
+                    ),
+                    _ => blit_to_window(
+                        glw,
+                        target.window_fb(),
+                        src,
+                        window_rect,
+                        AspectMode::Stretch,
+                        bar_color,
+                    ),
+                };
+                if let (Ok(()), Some(alpha)) = (blitted, &self.alpha) {
+                    alpha.apply(glw, window_rect);
+                }
+                if let Some(timers) = &mut self.timers {
+                    timers.blit.end(glw);
+                }
+                self.ctx.put_wnd(c)?;
+                blitted
+            }
+            (_, Some(local), RenderMode::OffscreenSingleContext) => {
+                let c = self.ctx.try_ct_wnd()?;
+                let (lw, lh) = local.size();
+                let blitted = blit_to_window(
+                    glw,
+                    local.fb(),
+                    Rect::from_size(lw, lh),
+                    window_rect,
+                    AspectMode::Stretch,
+                    bar_color,
+                );
+                if let (Ok(()), Some(alpha)) = (blitted, &self.alpha) {
+                    alpha.apply(glw, window_rect);
+                }
+                unsafe { glw.bind_framebuffer(glow::FRAMEBUFFER, None) };
+                self.ctx.put_wnd(c)?;
+                blitted
+            }
+            _ => Ok(()),
+        };
+        blitted.map_err(FrameError::Blit)
+    }
+
+    /// Presents the window surface in the window context.
+    fn swap(&mut self) -> glutin::error::Result<()> {
+        let c = self.ctx.try_ct_wnd()?;
+        let presented = self.ctx.present(ContextId::Window, &c);
+        self.ctx.put_wnd(c)?;
+        presented
+    }
+
+    fn last_gpu_times(&self) -> Option<GpuTimes> {
+        self.timers.as_ref()?.last_gpu_times()
+    }
+
+    fn shutdown(mut self) {
+        if self.presenter.is_some() || self.alpha.is_some() {
+            if let Ok(c) = self.ctx.try_ct_wnd() {
+                if let Some(presenter) = self.presenter.take() {
+                    presenter.delete(&self.glw);
+                }
+                if let Some(alpha) = self.alpha.take() {
+                    alpha.delete(&self.glw);
+                }
+                let _ = self.ctx.put_wnd(c);
+            }
+        }
+        self.ctx.shutdown();
+    }
+}
+
+/// Reports an error past setup, which has nowhere to go but the user, and
+/// ends the loop.
+pub(crate) fn fail(cf: &mut ControlFlow, what: &str, err: &dyn fmt::Display) {
+    eprintln!("{}: {}", what, err);
+    *cf = ControlFlow::Exit;
+}
+
+/// Recovers from an error that cost the contexts and redraws, fails with
+/// anything else. A failed make current skips the frame it happened in.
+fn recover_or_fail(
+    app: &mut App,
+    on_recover: &mut Option<Box<dyn FnMut(&glow::Context)>>,
+    window: &Window,
+    cf: &mut ControlFlow,
+    what: &str,
+    err: glutin::error::Error,
+) {
+    if !app.needs_recovery(&err) {
+        return fail(cf, &format!("failed to {}", what), &err);
+    }
+    eprintln!("contexts lost during {}: {}", what, err);
+    if let Err(err) = app.recover(on_recover.as_mut()) {
+        return fail(cf, "failed to recover lost context", &err);
+    }
+    window.request_redraw();
+}
+
+/// Runs a user callback on a clean error state and, if `check` is set, logs
+/// the errors it left behind so they are not blamed on the library.
+fn call_checked(
+    gl: &glow::Context,
+    name: &'static str,
+    check: bool,
+    f: &mut dyn FnMut(&glow::Context),
+) {
+    drain_gl_errors(gl);
+    f(gl);
+    if check {
+        for err in drain_gl_errors(gl) {
+            eprintln!("{} callback left GL error 0x{:04X}", name, err);
+            diagnostics::record_gl_error(name, err);
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! Dumps what the machine offers for each backend: the configs, the renderer
+//! and the limits that matter for sharing. Meant to be attached to bug reports.
+//!
+//! `cargo run --bin gl-probe [-- --json]`
+
+use glow::HasContext;
+use glutin::{
+    config::{Config, ConfigTemplateBuilder},
+    context::ContextAttributesBuilder,
+    display::Display,
+    prelude::{GlConfig, GlDisplay, PossiblyCurrentGlContext},
+};
+use glutin2_sharing::{
+    config_id, create_headless_surface, egl, load_gl, native_visual_id,
+    winit::{event_loop::EventLoop, window::WindowBuilder},
+    Backend, GlCaps, HeadlessSurface,
+};
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle, RawWindowHandle};
+
+const EGL_EXTENSIONS: [&str; 5] = [
+    "EGL_KHR_surfaceless_context",
+    "EGL_KHR_create_context",
+    "EGL_KHR_fence_sync",
+    "EGL_ANDROID_native_fence_sync",
+    "EGL_MESA_image_dma_buf_export",
+];
+const GL_EXTENSIONS: [&str; 4] = [
+    "GL_ARB_timer_query",
+    "GL_EXT_disjoint_timer_query",
+    "GL_OES_EGL_image",
+    "GL_EXT_texture_sRGB_decode",
+];
+
+struct ConfigInfo {
+    id: Option<i32>,
+    visual: Option<i32>,
+    color: String,
+    alpha: u8,
+    depth: u8,
+    stencil: u8,
+    samples: u8,
+    srgb: bool,
+}
+
+struct RendererInfo {
+    vendor: String,
+    renderer: String,
+    version: String,
+    caps: GlCaps,
+}
+
+struct BackendInfo {
+    backend: Backend,
+    error: Option<String>,
+    configs: Vec<ConfigInfo>,
+    renderer: Option<Result<RendererInfo, String>>,
+    egl_extensions: Vec<(&'static str, bool)>,
+}
+
+fn config_info(display: &Display, config: &Config) -> ConfigInfo {
+    ConfigInfo {
+        id: config_id(display, config),
+        visual: native_visual_id(display, config),
+        color: format!("{:?}", config.color_buffer_type()),
+        alpha: config.alpha_size(),
+        depth: config.depth_size(),
+        stencil: config.stencil_size(),
+        samples: config.num_samples(),
+        srgb: config.srgb_capable(),
+    }
+}
+
+fn renderer_info(
+    display: &Display,
+    config: &Config,
+    raw_wnd: RawWindowHandle,
+) -> Result<RendererInfo, String> {
+    let surface = create_headless_surface(display, config, raw_wnd, HeadlessSurface::Auto)
+        .map_err(|err| err.to_string())?;
+    let context =
+        unsafe { display.create_context(config, &ContextAttributesBuilder::new().build()) }
+            .map_err(|err| err.to_string())?;
+    let c = surface
+        .make_current(context)
+        .map_err(|err| err.to_string())?;
+    let gl = load_gl(&c);
+    let info = unsafe {
+        RendererInfo {
+            vendor: gl.get_parameter_string(glow::VENDOR),
+            renderer: gl.get_parameter_string(glow::RENDERER),
+            version: gl.get_parameter_string(glow::VERSION),
+            caps: GlCaps::query(&gl),
+        }
+    };
+    let _ = c.make_not_current();
+    Ok(info)
+}
+
+fn probe(backend: Backend, event_loop: &EventLoop<()>, raw_wnd: RawWindowHandle) -> BackendInfo {
+    let mut info = BackendInfo {
+        backend,
+        error: None,
+        configs: Vec::new(),
+        renderer: None,
+        egl_extensions: Vec::new(),
+    };
+    let display = match backend.create_display(event_loop.raw_display_handle()) {
+        Ok(display) => display,
+        Err(err) => {
+            info.error = Some(err.to_string());
+            return info;
+        }
+    };
+
+    let template = ConfigTemplateBuilder::new()
+        .compatible_with_native_window(raw_wnd)
+        .build();
+    let configs: Vec<Config> = match unsafe { display.find_configs(template) } {
+        Ok(configs) => configs.collect(),
+        Err(err) => {
+            info.error = Some(err.to_string());
+            return info;
+        }
+    };
+    info.configs = configs.iter().map(|c| config_info(&display, c)).collect();
+    info.renderer = configs
+        .first()
+        .map(|config| renderer_info(&display, config, raw_wnd));
+    if backend == Backend::Egl {
+        info.egl_extensions = EGL_EXTENSIONS
+            .iter()
+            .map(|&ext| (ext, egl::has_extension(&display, ext)))
+            .collect();
+    }
+    info
+}
+
+fn opt<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "-".to_owned(), |v| v.to_string())
+}
+
+fn print_text(infos: &[BackendInfo]) {
+    for info in infos {
+        println!("== {:?}", info.backend);
+        if let Some(err) = &info.error {
+            println!("unavailable: {}\n", err);
+            continue;
+        }
+        match &info.renderer {
+            Some(Ok(r)) => {
+                let (major, minor) = r.caps.version();
+                println!("vendor:   {}", r.vendor);
+                println!("renderer: {}", r.renderer);
+                println!(
+                    "version:  {} ({}.{}{})",
+                    r.version,
+                    major,
+                    minor,
+                    if r.caps.is_gles() { " es" } else { "" }
+                );
+                println!("max samples: {}", r.caps.max_samples());
+                println!("max renderbuffer size: {}", r.caps.max_renderbuffer_size());
+                println!("max viewports: {}", r.caps.max_viewports());
+                println!(
+                    "blit: {}, vao: {}, timer query: {}",
+                    r.caps.has_blit(),
+                    r.caps.has_vao(),
+                    r.caps.has_timer_query()
+                );
+                for ext in GL_EXTENSIONS {
+                    println!("{}: {}", ext, r.caps.has_extension(ext));
+                }
+            }
+            Some(Err(err)) => println!("no context: {}", err),
+            None => println!("no context: no config"),
+        }
+        for (ext, supported) in &info.egl_extensions {
+            println!("{}: {}", ext, supported);
+        }
+        println!("{} configs:", info.configs.len());
+        println!(
+            "  {:>5} {:>8} {:>5} {:>5} {:>7} {:>7} {:>5}  color",
+            "id", "visual", "alpha", "depth", "stencil", "samples", "srgb"
+        );
+        for c in &info.configs {
+            println!(
+                "  {:>5} {:>8} {:>5} {:>5} {:>7} {:>7} {:>5}  {}",
+                opt(c.id),
+                opt(c.visual),
+                c.alpha,
+                c.depth,
+                c.stencil,
+                c.samples,
+                c.srgb,
+                c.color
+            );
+        }
+        println!();
+    }
+}
+
+fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_opt(value: Option<i32>) -> String {
+    value.map_or_else(|| "null".to_owned(), |v| v.to_string())
+}
+
+fn json_flags<'a>(flags: impl Iterator<Item = (&'a str, bool)>) -> String {
+    let flags: Vec<_> = flags
+        .map(|(name, value)| format!("{}:{}", json_str(name), value))
+        .collect();
+    format!("{{{}}}", flags.join(","))
+}
+
+fn print_json(infos: &[BackendInfo]) {
+    let backends: Vec<_> = infos
+        .iter()
+        .map(|info| {
+            let mut fields = vec![format!("\"backend\":{}", json_str(&format!("{:?}", info.backend)))];
+            if let Some(err) = &info.error {
+                fields.push(format!("\"error\":{}", json_str(err)));
+            }
+            match &info.renderer {
+                Some(Ok(r)) => {
+                    let (major, minor) = r.caps.version();
+                    fields.push(format!(
+                        "\"renderer\":{{\"vendor\":{},\"renderer\":{},\"version\":{},\"gl_version\":[{},{}],\"gles\":{},\"max_samples\":{},\"max_renderbuffer_size\":{},\"max_viewports\":{},\"blit\":{},\"vao\":{},\"timer_query\":{},\"extensions\":{}}}",
+                        json_str(&r.vendor),
+                        json_str(&r.renderer),
+                        json_str(&r.version),
+                        major,
+                        minor,
+                        r.caps.is_gles(),
+                        r.caps.max_samples(),
+                        r.caps.max_renderbuffer_size(),
+                        r.caps.max_viewports(),
+                        r.caps.has_blit(),
+                        r.caps.has_vao(),
+                        r.caps.has_timer_query(),
+                        json_flags(GL_EXTENSIONS.iter().map(|&ext| (ext, r.caps.has_extension(ext)))),
+                    ));
+                }
+                Some(Err(err)) => fields.push(format!("\"renderer_error\":{}", json_str(err))),
+                None => {}
+            }
+            fields.push(format!(
+                "\"egl_extensions\":{}",
+                json_flags(info.egl_extensions.iter().copied())
+            ));
+            let configs: Vec<_> = info
+                .configs
+                .iter()
+                .map(|c| {
+                    format!(
+                        "{{\"id\":{},\"visual\":{},\"color\":{},\"alpha\":{},\"depth\":{},\"stencil\":{},\"samples\":{},\"srgb\":{}}}",
+                        json_opt(c.id),
+                        json_opt(c.visual),
+                        json_str(&c.color),
+                        c.alpha,
+                        c.depth,
+                        c.stencil,
+                        c.samples,
+                        c.srgb
+                    )
+                })
+                .collect();
+            fields.push(format!("\"configs\":[{}]", configs.join(",")));
+            format!("{{{}}}", fields.join(","))
+        })
+        .collect();
+    println!("[{}]", backends.join(","));
+}
+
+fn main() {
+    let json = std::env::args().skip(1).any(|arg| arg == "--json");
+
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_visible(false)
+        .build(&event_loop)
+        .unwrap();
+    let raw_wnd = window.raw_window_handle();
+
+    let infos: Vec<_> = [Backend::Egl, Backend::Glx]
+        .into_iter()
+        .map(|backend| probe(backend, &event_loop, raw_wnd))
+        .collect();
+    if json {
+        print_json(&infos);
+    } else {
+        print_text(&infos);
+    }
+}
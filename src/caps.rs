@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+
+use glow::HasContext;
+
+/// Capabilities of a context that differ between GL 2.1, 3.x core and GLES,
+/// queried once so code paths don't have to assume them.
+#[derive(Clone, Debug)]
+pub struct GlCaps {
+    version: (u32, u32),
+    is_gles: bool,
+    has_vao: bool,
+    has_blit: bool,
+    has_timer_query: bool,
+    has_map_buffer_range: bool,
+    has_texture_storage: bool,
+    forward_compatible: bool,
+    max_samples: u32,
+    max_renderbuffer_size: u32,
+    max_viewports: u32,
+    extensions: HashSet<String>,
+}
+
+impl GlCaps {
+    /// Queries the capabilities of the current context.
+    pub fn query(gl: &glow::Context) -> Self {
+        let version = gl.version();
+        let is_gles = version.is_embedded;
+        let extensions = gl.supported_extensions().clone();
+        let at_least = |major, minor| (version.major, version.minor) >= (major, minor);
+        let has = |name: &str| extensions.contains(name);
+
+        let has_vao = at_least(3, 0)
+            || has("GL_ARB_vertex_array_object")
+            || has("GL_OES_vertex_array_object")
+            || has("GL_APPLE_vertex_array_object");
+        let has_blit = at_least(3, 0)
+            || has("GL_ARB_framebuffer_object")
+            || has("GL_EXT_framebuffer_blit")
+            || has("GL_NV_framebuffer_blit");
+        let has_timer_query = (!is_gles && at_least(3, 3))
+            || has("GL_ARB_timer_query")
+            || has("GL_EXT_disjoint_timer_query");
+        // the GLES extensions suffix their entry points, which glow does not load
+        let has_map_buffer_range = at_least(3, 0) || (!is_gles && has("GL_ARB_map_buffer_range"));
+        let has_texture_storage = (!is_gles && (at_least(4, 2) || has("GL_ARB_texture_storage")))
+            || (is_gles && at_least(3, 0));
+        let has_viewport_array = !is_gles && (at_least(4, 1) || has("GL_ARB_viewport_array"));
+        let has_multisample = at_least(3, 0)
+            || has("GL_ARB_framebuffer_object")
+            || has("GL_EXT_framebuffer_multisample");
+
+        // GL_CONTEXT_FLAGS is desktop GL 3.0+, the bit does not exist on GLES
+        let forward_compatible = !is_gles
+            && at_least(3, 0)
+            && unsafe { gl.get_parameter_i32(glow::CONTEXT_FLAGS) } as u32
+                & glow::CONTEXT_FLAG_FORWARD_COMPATIBLE_BIT
+                != 0;
+
+        let max_samples = if has_multisample {
+            unsafe { gl.get_parameter_i32(glow::MAX_SAMPLES) }.max(0) as u32
+        } else {
+            0
+        };
+        let max_renderbuffer_size =
+            unsafe { gl.get_parameter_i32(glow::MAX_RENDERBUFFER_SIZE) }.max(0) as u32;
+        let max_viewports = if has_viewport_array {
+            unsafe { gl.get_parameter_i32(glow::MAX_VIEWPORTS) }.max(1) as u32
+        } else {
+            1
+        };
+
+        Self {
+            version: (version.major, version.minor),
+            is_gles,
+            has_vao,
+            has_blit,
+            has_timer_query,
+            has_map_buffer_range,
+            has_texture_storage,
+            forward_compatible,
+            max_samples,
+            max_renderbuffer_size,
+            max_viewports,
+            extensions,
+        }
+    }
+
+    pub fn version(&self) -> (u32, u32) {
+        self.version
+    }
+
+    pub fn is_gles(&self) -> bool {
+        self.is_gles
+    }
+
+    pub fn has_vao(&self) -> bool {
+        self.has_vao
+    }
+
+    /// Whether `glBlitFramebuffer` is available.
+    pub fn has_blit(&self) -> bool {
+        self.has_blit
+    }
+
+    pub fn has_timer_query(&self) -> bool {
+        self.has_timer_query
+    }
+
+    /// Whether `glMapBufferRange` is available (GL 3.0, GLES 3.0 or
+    /// `GL_ARB_map_buffer_range`).
+    pub fn has_map_buffer_range(&self) -> bool {
+        self.has_map_buffer_range
+    }
+
+    /// Whether `glTexStorage2D` is available (GL 4.2, GLES 3.0 or
+    /// `GL_ARB_texture_storage`).
+    pub fn has_texture_storage(&self) -> bool {
+        self.has_texture_storage
+    }
+
+    /// Whether deprecated functionality is removed from the context.
+    pub fn forward_compatible(&self) -> bool {
+        self.forward_compatible
+    }
+
+    pub fn max_samples(&self) -> u32 {
+        self.max_samples
+    }
+
+    pub fn max_renderbuffer_size(&self) -> u32 {
+        self.max_renderbuffer_size
+    }
+
+    /// How many viewports can be set at once, 1 without `glViewportArrayv`
+    /// (desktop GL 4.1 or `GL_ARB_viewport_array`).
+    pub fn max_viewports(&self) -> u32 {
+        self.max_viewports
+    }
+
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.contains(name)
+    }
+
+    pub fn extensions(&self) -> &HashSet<String> {
+        &self.extensions
+    }
+
+    /// `#version` line for the crate's shaders, which need `gl_VertexID` and
+    /// `in`/`out` qualifiers.
+    pub(crate) fn glsl_header(&self) -> &'static str {
+        match (self.is_gles, self.version >= (3, 3)) {
+            (true, _) => "#version 300 es",
+            (false, true) => "#version 330 core",
+            (false, false) => "#version 130",
+        }
+    }
+}
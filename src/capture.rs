@@ -0,0 +1,144 @@
+use glow::HasContext;
+
+use crate::{
+    caps::GlCaps,
+    context::{drain_gl_errors, ContextId, ContextWrapper},
+    rect::Rect,
+};
+
+/// Row order of captured images.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Origin {
+    /// First row is the top, as image files expect. Costs a copy to flip.
+    #[default]
+    TopLeft,
+    /// First row is the bottom, as GL returns it.
+    BottomLeft,
+}
+
+/// Reads back a `width` x `height` RGBA8 image from the currently bound read
+/// framebuffer, with rows ordered top to bottom.
+pub fn read_rgba(gl: &glow::Context, width: u32, height: u32) -> Vec<u8> {
+    capture_frame_with(gl, width, height, Origin::TopLeft)
+}
+
+/// Like [`read_rgba`], with rows ordered according to `origin`.
+pub fn capture_frame_with(gl: &glow::Context, width: u32, height: u32, origin: Origin) -> Vec<u8> {
+    capture_rect_with(gl, Rect::from_size(width, height), origin)
+}
+
+/// Reads back only `rect` of the currently bound read framebuffer, e.g. the
+/// headless framebuffer of a [`SharedTarget`](crate::SharedTarget), as
+/// tightly packed RGBA8 with rows ordered top to bottom.
+pub fn capture_rect(gl: &glow::Context, rect: Rect) -> Vec<u8> {
+    capture_rect_with(gl, rect, Origin::TopLeft)
+}
+
+/// Like [`capture_rect`], with rows ordered according to `origin`. The pack
+/// state is reset for the read and restored afterwards.
+pub fn capture_rect_with(gl: &glow::Context, rect: Rect, origin: Origin) -> Vec<u8> {
+    const PACK_STATE: [(u32, i32); 4] = [
+        (glow::PACK_ALIGNMENT, 1),
+        (glow::PACK_ROW_LENGTH, 0),
+        (glow::PACK_SKIP_PIXELS, 0),
+        (glow::PACK_SKIP_ROWS, 0),
+    ];
+
+    let (width, height) = (rect.width, rect.height);
+    let mut pixels = vec![0; width as usize * height as usize * 4];
+    if pixels.is_empty() {
+        return pixels;
+    }
+    unsafe {
+        let saved = PACK_STATE.map(|(param, _)| gl.get_parameter_i32(param));
+        for (param, value) in PACK_STATE {
+            gl.pixel_store_i32(param, value);
+        }
+        gl.read_pixels(
+            rect.x,
+            rect.y,
+            width as _,
+            height as _,
+            glow::RGBA,
+            glow::UNSIGNED_BYTE,
+            glow::PixelPackData::Slice(&mut pixels),
+        );
+        for ((param, _), value) in PACK_STATE.into_iter().zip(saved) {
+            gl.pixel_store_i32(param, value);
+        }
+    }
+    if origin == Origin::BottomLeft {
+        return pixels;
+    }
+
+    let stride = width as usize * 4;
+    let mut flipped = Vec::with_capacity(pixels.len());
+    for row in pixels.chunks_exact(stride).rev() {
+        flipped.extend_from_slice(row);
+    }
+    flipped
+}
+
+/// Reads back what the window shows, rows top to bottom, with the size of
+/// [`ContextWrapper::surface_size`]. Desktop GL reads the front buffer, i.e.
+/// the last presented frame. GLES and drivers refusing `GL_FRONT` read the
+/// back buffer instead, which only holds that frame until the next swap.
+/// Neither context may be current.
+pub fn capture_window(ctx: &mut ContextWrapper) -> glutin::error::Result<Vec<u8>> {
+    let (width, height) = ctx.surface_size(ContextId::Window);
+    let c = ctx.try_ct_wnd()?;
+    let gl = ctx.gl(ContextId::Window);
+    let pixels = unsafe {
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+        let read_buffer = gl.get_parameter_i32(glow::READ_BUFFER) as u32;
+        if !GlCaps::query(gl).is_gles() {
+            drain_gl_errors(gl);
+            gl.read_buffer(glow::FRONT);
+            if !drain_gl_errors(gl).is_empty() {
+                gl.read_buffer(glow::BACK);
+            }
+        }
+        let pixels = read_rgba(gl, width, height);
+        gl.read_buffer(read_buffer);
+        pixels
+    };
+    ctx.put_wnd(c)?;
+    Ok(pixels)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ImageDiff {
+    pub max: u8,
+    pub mean: f64,
+}
+
+/// Per channel difference of two equally sized images, `None` if the sizes differ.
+pub fn diff_rgba(a: &[u8], b: &[u8]) -> Option<ImageDiff> {
+    if a.len() != b.len() {
+        return None;
+    }
+
+    let mut max = 0;
+    let mut sum = 0u64;
+    for (a, b) in a.iter().zip(b) {
+        let d = a.abs_diff(*b);
+        max = max.max(d);
+        sum += d as u64;
+    }
+
+    Some(ImageDiff {
+        max,
+        mean: if a.is_empty() {
+            0.0
+        } else {
+            sum as f64 / a.len() as f64
+        },
+    })
+}
+
+#[cfg(feature = "image")]
+pub fn load_rgba(path: impl AsRef<std::path::Path>) -> image::ImageResult<(Vec<u8>, u32, u32)> {
+    let img = image::open(path)?.into_rgba8();
+    let (width, height) = img.dimensions();
+    Ok((img.into_raw(), width, height))
+}
@@ -0,0 +1,278 @@
+use glow::HasContext;
+
+use crate::{
+    caps::GlCaps,
+    context::{ContextId, ContextWrapper, GlObject, ObjectError},
+    present::{AspectMode, QuadPresenter, SourceTexture},
+    rect::Rect,
+};
+
+/// Handle to a layer of a [`Compositor`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LayerId(u32);
+
+/// How a layer is blended onto the layers below it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Overwrites what is below, alpha included.
+    Replace,
+    /// Straight alpha.
+    #[default]
+    Alpha,
+    /// Alpha already multiplied into the color.
+    Premultiplied,
+    /// Adds the color weighted by its alpha.
+    Additive,
+}
+
+impl BlendMode {
+    fn apply(self, gl: &glow::Context) {
+        let (src, dst) = match self {
+            BlendMode::Replace => {
+                unsafe { gl.disable(glow::BLEND) };
+                return;
+            }
+            BlendMode::Alpha => (glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Premultiplied => (glow::ONE, glow::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Additive => (glow::SRC_ALPHA, glow::ONE),
+        };
+        unsafe {
+            gl.enable(glow::BLEND);
+            gl.blend_func_separate(src, dst, glow::ONE, glow::ONE_MINUS_SRC_ALPHA);
+        }
+    }
+}
+
+/// What a layer shows.
+pub enum LayerSource {
+    /// A texture of the share group owned by other code, e.g.
+    /// [`SharedTarget::texture`](crate::SharedTarget::texture).
+    Texture(glow::NativeTexture, (u32, u32)),
+    /// A texture of this size the callback renders into in the headless
+    /// context every frame, with a viewport covering it and cleared to
+    /// transparent.
+    Render((u32, u32), Box<dyn FnMut(&glow::Context)>),
+}
+
+struct Layer {
+    id: LayerId,
+    texture: glow::NativeTexture,
+    size: (u32, u32),
+    render: Option<(glow::NativeFramebuffer, Box<dyn FnMut(&glow::Context)>)>,
+    blend: BlendMode,
+    z: i32,
+}
+
+/// Draws layers onto the window back to front with a [`QuadPresenter`], each
+/// stretched over the presented area and blended according to its mode.
+/// Layers with the same `z` are drawn in the order they were added.
+pub struct Compositor {
+    presenter: QuadPresenter,
+    layers: Vec<Layer>,
+    next_id: u32,
+}
+
+impl Compositor {
+    /// Builds the presenter in the window context, it is deleted with
+    /// [`Self::delete`].
+    pub fn new(
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        caps: &GlCaps,
+    ) -> Result<Self, ObjectError> {
+        let c = ctx.try_ct_wnd()?;
+        let presenter = QuadPresenter::new(glw, caps);
+        ctx.put_wnd(c)?;
+        Ok(Self {
+            presenter: presenter?,
+            layers: Vec::new(),
+            next_id: 0,
+        })
+    }
+
+    /// Adds a layer at depth `z`, higher is drawn later. A rendered layer
+    /// gets an RGBA8 texture and a framebuffer in the headless context, which
+    /// are deleted on [`ContextWrapper::shutdown`].
+    pub fn add_layer(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glh: &glow::Context,
+        source: LayerSource,
+        blend: BlendMode,
+        z: i32,
+    ) -> Result<LayerId, ObjectError> {
+        let (texture, size, render) = match source {
+            LayerSource::Texture(texture, size) => (texture, size, None),
+            LayerSource::Render((width, height), render) => {
+                let c = ctx.try_ct_head()?;
+                let created = unsafe { create_layer_target(ctx, glh, width, height) };
+                ctx.put_head(c)?;
+                let (texture, fb) = created?;
+                (texture, (width, height), Some((fb, render)))
+            }
+        };
+        let id = LayerId(self.next_id);
+        self.next_id += 1;
+        self.layers.push(Layer {
+            id,
+            texture,
+            size,
+            render,
+            blend,
+            z,
+        });
+        // stable, so equal depths keep the order they were added in
+        self.layers.sort_by_key(|layer| layer.z);
+        Ok(id)
+    }
+
+    /// Removes a layer, deleting what [`Self::add_layer`] created for it.
+    /// Returns whether it existed.
+    pub fn remove_layer(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glh: &glow::Context,
+        id: LayerId,
+    ) -> glutin::error::Result<bool> {
+        let index = match self.layers.iter().position(|layer| layer.id == id) {
+            Some(index) => index,
+            None => return Ok(false),
+        };
+        if let Some((fb, _)) = &self.layers[index].render {
+            let (fb, texture) = (*fb, self.layers[index].texture);
+            let c = ctx.try_ct_head()?;
+            unsafe {
+                glh.delete_framebuffer(fb);
+                glh.delete_texture(texture);
+            }
+            ctx.untrack(ContextId::Headless, GlObject::Framebuffer(fb));
+            ctx.untrack(ContextId::Headless, GlObject::Texture(texture));
+            ctx.put_head(c)?;
+        }
+        self.layers.remove(index);
+        Ok(true)
+    }
+
+    pub fn set_blend_mode(&mut self, id: LayerId, blend: BlendMode) {
+        if let Some(layer) = self.layers.iter_mut().find(|layer| layer.id == id) {
+            layer.blend = blend;
+        }
+    }
+
+    pub fn set_z(&mut self, id: LayerId, z: i32) {
+        if let Some(layer) = self.layers.iter_mut().find(|layer| layer.id == id) {
+            layer.z = z;
+        }
+        self.layers.sort_by_key(|layer| layer.z);
+    }
+
+    /// Runs the callbacks of rendered layers in the headless context, then
+    /// clears `dst` of the window to `clear_color` and draws every layer onto
+    /// it, and presents the window. Layers without area are skipped.
+    pub fn present(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        glh: &glow::Context,
+        dst: Rect,
+        clear_color: [f32; 4],
+    ) -> glutin::error::Result<()> {
+        if self.layers.iter().any(|layer| layer.render.is_some()) {
+            let c = ctx.try_ct_head()?;
+            for layer in &mut self.layers {
+                if let Some((fb, render)) = &mut layer.render {
+                    unsafe {
+                        glh.bind_framebuffer(glow::FRAMEBUFFER, Some(*fb));
+                        glh.clear_color(0.0, 0.0, 0.0, 0.0);
+                        glh.clear(glow::COLOR_BUFFER_BIT);
+                    }
+                    Rect::from_size(layer.size.0, layer.size.1).viewport(glh);
+                    render(glh);
+                }
+            }
+            unsafe {
+                glh.bind_framebuffer(glow::FRAMEBUFFER, None);
+                glh.flush();
+            }
+            ctx.put_head(c)?;
+        }
+
+        let c = ctx.try_ct_wnd()?;
+        let [r, g, b, a] = clear_color;
+        unsafe {
+            glw.bind_framebuffer(glow::FRAMEBUFFER, None);
+            glw.enable(glow::SCISSOR_TEST);
+            dst.scissor(glw);
+            glw.clear_color(r, g, b, a);
+            glw.clear(glow::COLOR_BUFFER_BIT);
+            glw.disable(glow::SCISSOR_TEST);
+        }
+        for layer in &self.layers {
+            layer.blend.apply(glw);
+            let (width, height) = layer.size;
+            // an empty layer or `dst` has nothing to draw, the rest still does
+            let _ = self.presenter.draw(
+                glw,
+                SourceTexture {
+                    texture: layer.texture,
+                    size: layer.size,
+                },
+                Rect::from_size(width, height),
+                dst,
+                AspectMode::Stretch,
+                clear_color,
+            );
+        }
+        unsafe { glw.disable(glow::BLEND) };
+        let presented = ctx.present(ContextId::Window, &c);
+        ctx.put_wnd(c)?;
+        presented
+    }
+
+    /// Deletes the presenter, the objects of rendered layers are left to
+    /// [`ContextWrapper::shutdown`].
+    pub fn delete(
+        self,
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+    ) -> glutin::error::Result<()> {
+        let c = ctx.try_ct_wnd()?;
+        self.presenter.delete(glw);
+        ctx.put_wnd(c)?;
+        Ok(())
+    }
+}
+
+unsafe fn create_layer_target(
+    ctx: &mut ContextWrapper,
+    glh: &glow::Context,
+    width: u32,
+    height: u32,
+) -> Result<(glow::NativeTexture, glow::NativeFramebuffer), String> {
+    let texture = glh.create_texture()?;
+    ctx.track_head(GlObject::Texture(texture));
+    glh.bind_texture(glow::TEXTURE_2D, Some(texture));
+    glh.tex_storage_2d(glow::TEXTURE_2D, 1, glow::RGBA8, width as _, height as _);
+    for (param, value) in [
+        (glow::TEXTURE_MIN_FILTER, glow::LINEAR),
+        (glow::TEXTURE_MAG_FILTER, glow::LINEAR),
+        (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+        (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+    ] {
+        glh.tex_parameter_i32(glow::TEXTURE_2D, param, value as i32);
+    }
+    glh.bind_texture(glow::TEXTURE_2D, None);
+
+    let fb = glh.create_framebuffer()?;
+    ctx.track_head(GlObject::Framebuffer(fb));
+    glh.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+    glh.framebuffer_texture_2d(
+        glow::FRAMEBUFFER,
+        glow::COLOR_ATTACHMENT0,
+        glow::TEXTURE_2D,
+        Some(texture),
+        0,
+    );
+    glh.bind_framebuffer(glow::FRAMEBUFFER, None);
+    Ok((texture, fb))
+}
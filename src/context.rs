@@ -0,0 +1,1478 @@
+use std::{
+    ffi::{c_void, CString},
+    fmt,
+    num::NonZeroU32,
+    rc::Rc,
+    thread::{self, ThreadId},
+};
+
+use glow::HasContext;
+use glutin::{
+    config::{ColorBufferType, Config},
+    context::{
+        AsRawContext, ContextApi, ContextAttributesBuilder, GlProfile, NotCurrentContext,
+        PossiblyCurrentContext, RawContext, Robustness, Version,
+    },
+    display::Display,
+    error::ErrorKind,
+    prelude::{
+        GetGlConfig, GlConfig, GlDisplay, NotCurrentGlContextSurfaceAccessor,
+        PossiblyCurrentGlContext,
+    },
+    surface::{GlSurface, Surface, SwapInterval, WindowSurface},
+};
+use raw_window_handle::RawWindowHandle;
+
+use crate::{
+    caps::GlCaps,
+    display::{
+        config_id, find_headless_config, swap_interval_range, Backend, ColorDepth, EffectiveConfig,
+    },
+    egl,
+    present::ShareObject,
+    rect::Rect,
+    surface::{
+        create_pbuffer, AnySurface, Buffering, HeadlessPresent, PresentCaps, SurfaceInfo,
+        SurfaceKind,
+    },
+    target::SharedTarget,
+};
+
+/// A context from [`ContextWrapper::spawn_worker_context`], to be sent to the
+/// worker thread that uses it.
+pub struct WorkerContext {
+    context: NotCurrentContext,
+    /// Display and config to create a pbuffer with, without surfaceless
+    /// support.
+    pbuffer: Option<(Display, Config)>,
+}
+
+impl WorkerContext {
+    /// Makes the context current on the calling thread. The returned surface
+    /// is the one it is current on, keep it alive as long as the context.
+    pub fn make_current(self) -> glutin::error::Result<(PossiblyCurrentContext, AnySurface)> {
+        let surface = match &self.pbuffer {
+            Some((display, config)) => AnySurface::Pbuffer(create_pbuffer(display, config)?),
+            None => AnySurface::Surfaceless,
+        };
+        let context = surface.make_current(self.context)?;
+        Ok((context, surface))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GlObject {
+    Framebuffer(glow::NativeFramebuffer),
+    Renderbuffer(glow::NativeRenderbuffer),
+    Texture(glow::NativeTexture),
+    Query(glow::NativeQuery),
+    Buffer(glow::NativeBuffer),
+}
+
+impl GlObject {
+    unsafe fn delete(self, gl: &glow::Context) {
+        match self {
+            GlObject::Framebuffer(fb) => gl.delete_framebuffer(fb),
+            GlObject::Renderbuffer(rb) => gl.delete_renderbuffer(rb),
+            GlObject::Texture(tex) => gl.delete_texture(tex),
+            GlObject::Query(query) => gl.delete_query(query),
+            GlObject::Buffer(buffer) => gl.delete_buffer(buffer),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContextId {
+    Window,
+    Headless,
+}
+
+// fields are dropped in declaration order: surfaces, then contexts, then the display
+pub struct ContextWrapper {
+    pub window_surface: Surface<WindowSurface>,
+    pub headless_surface: AnySurface,
+    pub window: Option<NotCurrentContext>,
+    pub headless: Option<NotCurrentContext>,
+    pub display: Display,
+    /// Kept to recreate the contexts even after losing them, see [`Self::recover`].
+    configs: (Config, Config),
+    /// [`config_id`]s of the configs, to check the surfaces against.
+    config_ids: (Option<i32>, Option<i32>),
+    /// Raw surfaces that passed [`Self::check_surface_config`].
+    checked_surfaces: (Option<*const c_void>, Option<*const c_void>),
+    window_objects: Vec<GlObject>,
+    headless_objects: Vec<GlObject>,
+    swap_interval: Option<SwapInterval>,
+    headless_present: Option<HeadlessPresent>,
+    current: Option<(ContextId, ThreadId)>,
+    /// Platform handles of both contexts, to compare with the one the driver
+    /// has current even while they are checked out.
+    raw_contexts: (RawContext, RawContext),
+    window_gl: Rc<glow::Context>,
+    headless_gl: Rc<glow::Context>,
+}
+
+impl ContextWrapper {
+    /// Wraps the contexts and loads a `glow::Context` for each, which makes
+    /// both current once. Fails if either cannot be made current.
+    pub fn new(
+        display: Display,
+        window_surface: Surface<WindowSurface>,
+        headless_surface: impl Into<AnySurface>,
+        window: NotCurrentContext,
+        headless: NotCurrentContext,
+    ) -> glutin::error::Result<Self> {
+        let configs = (window.config(), headless.config());
+        let raw_contexts = (window.raw_context(), headless.raw_context());
+        let config_ids = (
+            config_id(&display, &configs.0),
+            config_id(&display, &configs.1),
+        );
+
+        let c = window.make_current(&window_surface)?;
+        let window_gl = Rc::new(load_gl(&c));
+        let window = c.make_not_current()?;
+
+        let headless_surface = headless_surface.into();
+        let c = headless_surface.make_current(headless)?;
+        let headless_gl = Rc::new(load_gl(&c));
+        let headless = c.make_not_current()?;
+
+        Ok(Self {
+            window_surface,
+            headless_surface,
+            window: Some(window),
+            headless: Some(headless),
+            display,
+            configs,
+            config_ids,
+            checked_surfaces: (None, None),
+            window_objects: Vec::new(),
+            headless_objects: Vec::new(),
+            swap_interval: None,
+            headless_present: None,
+            current: None,
+            raw_contexts,
+            window_gl,
+            headless_gl,
+        })
+    }
+
+    /// Replaces both contexts with new ones sharing the way
+    /// [`create_shared_contexts`] does, e.g. after a context loss, and reloads
+    /// their `glow::Context`s. Neither context may be checked out.
+    ///
+    /// Every GL object of the old share group is gone, so tracked objects are
+    /// forgotten rather than deleted and anything built on the old contexts,
+    /// like a [`SharedTarget`](crate::SharedTarget), has to be created again.
+    /// Handles from [`Self::gl_handle`] keep pointing at the old contexts.
+    /// Also replaces contexts a failed make current took with it.
+    pub fn recover(
+        &mut self,
+        raw_wnd: RawWindowHandle,
+        options: ContextOptions,
+    ) -> Result<(), CreateError> {
+        let (config, headless_config) = self.configs.clone();
+
+        // drop the old contexts before creating new ones on the same surfaces
+        self.window = None;
+        self.headless = None;
+        self.window_objects.clear();
+        self.headless_objects.clear();
+
+        let (window, headless) = create_shared_contexts(
+            &self.display,
+            &config,
+            Some(&headless_config),
+            raw_wnd,
+            &self.headless_surface,
+            options,
+        )?;
+        self.raw_contexts = (window.raw_context(), headless.raw_context());
+        self.window = Some(window);
+        self.headless = Some(headless);
+
+        let c = self.try_ct_wnd()?;
+        self.window_gl = Rc::new(load_gl(&c));
+        self.put_wnd(c)?;
+
+        let c = self.try_ct_head()?;
+        self.headless_gl = Rc::new(load_gl(&c));
+        self.put_head(c)?;
+
+        Ok(())
+    }
+
+    /// The `glow::Context` loaded for context `id`. GL calls through it need
+    /// that context to be current.
+    pub fn gl(&self, id: ContextId) -> &glow::Context {
+        match id {
+            ContextId::Window => &self.window_gl,
+            ContextId::Headless => &self.headless_gl,
+        }
+    }
+
+    /// Shared handle to [`Self::gl`], for holding on to it while borrowing the
+    /// wrapper mutably.
+    pub fn gl_handle(&self, id: ContextId) -> Rc<glow::Context> {
+        match id {
+            ContextId::Window => Rc::clone(&self.window_gl),
+            ContextId::Headless => Rc::clone(&self.headless_gl),
+        }
+    }
+
+    /// Checks out the window context, current with the window surface. Fails
+    /// with [`ErrorKind::BadContextState`] if it is already checked out, and
+    /// with [`ErrorKind::BadMatch`] on a surface of another config. A
+    /// failed make current, e.g. on a surface the display invalidated, takes
+    /// the context with it, see [`Self::has_contexts`].
+    pub fn try_ct_wnd(&mut self) -> glutin::error::Result<PossiblyCurrentContext> {
+        self.check_surface_config(ContextId::Window)?;
+        let window = self.window.take().ok_or(ErrorKind::BadContextState)?;
+        let c = window.make_current(&self.window_surface)?;
+        self.current = Some((ContextId::Window, thread::current().id()));
+        Ok(c)
+    }
+
+    /// Like [`Self::try_ct_wnd`] for the headless context and surface.
+    pub fn try_ct_head(&mut self) -> glutin::error::Result<PossiblyCurrentContext> {
+        self.check_surface_config(ContextId::Headless)?;
+        let headless = self.headless.take().ok_or(ErrorKind::BadContextState)?;
+        let c = self.headless_surface.make_current(headless)?;
+        self.current = Some((ContextId::Headless, thread::current().id()));
+        Ok(c)
+    }
+
+    /// Fails with [`ErrorKind::BadMatch`] if the surface of `id` was created
+    /// from another config than its context, which makes rendering undefined.
+    /// Only EGL can tell, and each surface is checked once.
+    fn check_surface_config(&mut self, id: ContextId) -> glutin::error::Result<()> {
+        let (surface, expected, checked) = match id {
+            ContextId::Window => (
+                egl::raw_surface(&self.window_surface),
+                self.config_ids.0,
+                &mut self.checked_surfaces.0,
+            ),
+            ContextId::Headless => (
+                self.headless_surface.raw_egl(),
+                self.config_ids.1,
+                &mut self.checked_surfaces.1,
+            ),
+        };
+        let surface = match surface {
+            Some(surface) if *checked != Some(surface) => surface,
+            _ => return Ok(()),
+        };
+        match (expected, egl::surface_config_id(&self.display, surface)) {
+            (Some(expected), Some(actual)) if expected != actual => Err(ErrorKind::BadMatch.into()),
+            _ => {
+                *checked = Some(surface);
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether neither context is checked out or lost to a failed make
+    /// current. Lost contexts come back with [`Self::recover`].
+    pub fn has_contexts(&self) -> bool {
+        self.window.is_some() && self.headless.is_some()
+    }
+
+    /// Puts back the window context checked out with [`Self::try_ct_wnd`],
+    /// releasing it. A failed release takes the context with it like a
+    /// failed make current, see [`Self::has_contexts`].
+    pub fn put_wnd(&mut self, ctx: PossiblyCurrentContext) -> glutin::error::Result<()> {
+        self.current = None;
+        self.window = Some(ctx.make_not_current()?);
+        Ok(())
+    }
+
+    /// Like [`Self::put_wnd`] for the headless context.
+    pub fn put_head(&mut self, ctx: PossiblyCurrentContext) -> glutin::error::Result<()> {
+        self.current = None;
+        self.headless = Some(ctx.make_not_current()?);
+        Ok(())
+    }
+
+    /// Leaves no context current on the calling thread, e.g. before handing it
+    /// to other GL code. Making the window context current and releasing it
+    /// again also releases whatever context other code left current. Fails
+    /// with [`ErrorKind::BadContextState`] if a context is checked out, which
+    /// only its holder can release, or is still current according to
+    /// [`Self::is_current`] afterwards.
+    pub fn release_all(&mut self) -> glutin::error::Result<()> {
+        if self.headless.is_none() {
+            return Err(ErrorKind::BadContextState.into());
+        }
+        let c = self.try_ct_wnd()?;
+        self.put_wnd(c)?;
+        if self.is_current(ContextId::Window) || self.is_current(ContextId::Headless) {
+            return Err(ErrorKind::BadContextState.into());
+        }
+        Ok(())
+    }
+
+    /// Whether context `id` is current on the calling thread. On EGL and GLX
+    /// the driver is asked, like [`PossiblyCurrentGlContext::is_current`]
+    /// does, so contexts other code made current or released are seen too.
+    /// Elsewhere this only knows about checkouts through
+    /// [`Self::try_ct_wnd`]/[`Self::try_ct_head`] that were not put back.
+    pub fn is_current(&self, id: ContextId) -> bool {
+        let raw = match id {
+            ContextId::Window => &self.raw_contexts.0,
+            ContextId::Headless => &self.raw_contexts.1,
+        };
+        match driver_is_current(&self.display, raw) {
+            Some(current) => current,
+            None => self.current == Some((id, thread::current().id())),
+        }
+    }
+
+    /// Size as reported by the surface itself, which may differ from the last
+    /// requested size.
+    pub fn surface_size(&self, id: ContextId) -> (u32, u32) {
+        let (width, height) = match id {
+            ContextId::Window => (self.window_surface.width(), self.window_surface.height()),
+            ContextId::Headless => (
+                self.headless_surface.width(),
+                self.headless_surface.height(),
+            ),
+        };
+        (width.unwrap_or(0), height.unwrap_or(0))
+    }
+
+    /// The buffering the surface of `id` was granted, which decides whether
+    /// [`Self::present`] swaps or flushes.
+    pub fn buffering(&self, id: ContextId) -> Buffering {
+        match id {
+            ContextId::Window => Buffering::of_surface(&self.window_surface),
+            ContextId::Headless => self.headless_surface.buffering(),
+        }
+    }
+
+    /// What the surface of `id` was actually created with, as opposed to what
+    /// was requested.
+    pub fn surface_info(&self, id: ContextId) -> SurfaceInfo {
+        let kind = match id {
+            ContextId::Window => SurfaceKind::Window,
+            ContextId::Headless => self.headless_surface.kind(),
+        };
+        let (width, height) = self.surface_size(id);
+        SurfaceInfo {
+            kind,
+            buffering: self.buffering(id),
+            width,
+            height,
+            swap_interval: kind == SurfaceKind::Window,
+        }
+    }
+
+    /// Whether the contexts render directly rather than through the X server,
+    /// `None` on backends other than GLX or while both are checked out.
+    /// [`create_shared_contexts`] already refuses indirect contexts.
+    pub fn is_direct_rendering(&self) -> Option<bool> {
+        let context = self.window.as_ref().or(self.headless.as_ref())?;
+        #[cfg(target_os = "linux")]
+        let direct = crate::x11::glx_is_direct(&self.display, context.raw_context());
+        #[cfg(not(target_os = "linux"))]
+        let direct = {
+            let _ = context;
+            None
+        };
+        direct
+    }
+
+    /// Swap intervals and present modes the surface of `id` supports, to only
+    /// offer valid ones.
+    pub fn present_capabilities(&self, id: ContextId) -> PresentCaps {
+        let config = match id {
+            ContextId::Window => &self.configs.0,
+            ContextId::Headless => &self.configs.1,
+        };
+        #[cfg(target_os = "linux")]
+        let adaptive = crate::x11::glx_has_extension(&self.display, "GLX_EXT_swap_control_tear");
+        #[cfg(not(target_os = "linux"))]
+        let adaptive = false;
+        PresentCaps {
+            swap_interval: swap_interval_range(&self.display, config),
+            adaptive,
+            buffering: self.buffering(id),
+        }
+    }
+
+    /// Presents the surface of `id`, `c` must be its current context. The
+    /// window surface goes through [`present`](crate::present), the headless
+    /// one according to [`Self::headless_present`].
+    pub fn present(&self, id: ContextId, c: &PossiblyCurrentContext) -> glutin::error::Result<()> {
+        let gl = self.gl(id);
+        match (id, self.headless_present()) {
+            (ContextId::Window, _) => crate::surface::present(&self.window_surface, c, gl),
+            (ContextId::Headless, HeadlessPresent::None) => Ok(()),
+            (ContextId::Headless, HeadlessPresent::Flush) => {
+                unsafe { gl.flush() };
+                Ok(())
+            }
+            (ContextId::Headless, HeadlessPresent::Swap) => self.headless_surface.present(c, gl),
+        }
+    }
+
+    /// Overrides what [`Self::present`] does for the headless surface, `None`
+    /// goes back to [`HeadlessPresent::default_for`] its kind.
+    pub fn set_headless_present(&mut self, present: Option<HeadlessPresent>) {
+        self.headless_present = present;
+    }
+
+    pub fn headless_present(&self) -> HeadlessPresent {
+        self.headless_present
+            .unwrap_or_else(|| HeadlessPresent::default_for(self.headless_surface.kind()))
+    }
+
+    /// Resizes the window surface, then the headless surface and `target` to
+    /// the size the window surface actually ended up with, which is returned.
+    /// A zero size, e.g. of a minimized window, leaves everything as is.
+    /// Neither context may be current.
+    pub fn resize(
+        &mut self,
+        (width, height): (u32, u32),
+        target: Option<&mut SharedTarget>,
+    ) -> glutin::error::Result<(u32, u32)> {
+        let size = self.resize_window((width, height))?;
+        if let Some(target) = target {
+            self.resize_offscreen(size, target)?;
+        }
+        Ok(size)
+    }
+
+    /// The window half of [`Self::resize`].
+    pub(crate) fn resize_window(
+        &mut self,
+        (width, height): (u32, u32),
+    ) -> glutin::error::Result<(u32, u32)> {
+        let (width, height) = match (NonZeroU32::new(width), NonZeroU32::new(height)) {
+            (Some(width), Some(height)) => (width, height),
+            _ => return Ok(self.surface_size(ContextId::Window)),
+        };
+        let c = self.try_ct_wnd()?;
+        self.window_surface.resize(&c, width, height);
+        // some platforms only apply the new size on the next swap
+        let swapped = self.window_surface.swap_buffers(&c);
+        self.put_wnd(c)?;
+        swapped.map(|()| self.surface_size(ContextId::Window))
+    }
+
+    /// The offscreen half of [`Self::resize`], `size` being the window size.
+    pub(crate) fn resize_offscreen(
+        &mut self,
+        (width, height): (u32, u32),
+        target: &mut SharedTarget,
+    ) -> glutin::error::Result<()> {
+        let (nz_width, nz_height) = match (NonZeroU32::new(width), NonZeroU32::new(height)) {
+            (Some(width), Some(height)) => (width, height),
+            _ => return Ok(()),
+        };
+        let glw = self.gl_handle(ContextId::Window);
+        target.resize(self, &glw, width, height)?;
+
+        let c = self.try_ct_head()?;
+        self.headless_surface.resize(&c, nz_width, nz_height);
+        // nothing reads the headless surface, it renders into the target
+        let swapped = match self.headless_present() {
+            HeadlessPresent::Swap => self.headless_surface.swap_buffers(&c),
+            HeadlessPresent::None | HeadlessPresent::Flush => Ok(()),
+        };
+        self.put_head(c)?;
+        swapped
+    }
+
+    /// Sets the swap interval of the window surface, `c` must be the current
+    /// window context.
+    pub fn set_swap_interval(
+        &mut self,
+        c: &PossiblyCurrentContext,
+        interval: SwapInterval,
+    ) -> glutin::error::Result<()> {
+        self.window_surface.set_swap_interval(c, interval)?;
+        self.swap_interval = Some(interval);
+        Ok(())
+    }
+
+    /// The last swap interval successfully set through [`Self::set_swap_interval`].
+    /// Backends have no way to query the interval in effect, so this is `None`
+    /// while the driver default is used.
+    pub fn swap_interval(&self) -> Option<SwapInterval> {
+        self.swap_interval
+    }
+
+    /// Limits the area the compositor updates on the next window swap to
+    /// `rects`, via `EGL_KHR_partial_update`. Must be called with the window
+    /// context current, before anything is rendered to the window this frame.
+    pub fn set_damage_region(&self, rects: &[Rect]) -> Result<(), PartialUpdateError> {
+        if !egl::has_extension(&self.display, "EGL_KHR_partial_update") {
+            return Err(PartialUpdateError::Unsupported);
+        }
+        let dpy = egl::raw_display(&self.display).ok_or(PartialUpdateError::Unsupported)?;
+        let surface =
+            egl::raw_surface(&self.window_surface).ok_or(PartialUpdateError::Unsupported)?;
+        let set_damage_region: EglSetDamageRegionKhr =
+            egl::load(&self.display, b"eglSetDamageRegionKHR\0")
+                .ok_or(PartialUpdateError::Unsupported)?;
+
+        let mut raw_rects: Vec<i32> = rects
+            .iter()
+            .flat_map(|r| [r.x, r.y, r.width as i32, r.height as i32])
+            .collect();
+        let ok =
+            unsafe { set_damage_region(dpy, surface, raw_rects.as_mut_ptr(), rects.len() as i32) };
+        if ok == 0 {
+            return Err(PartialUpdateError::Failed);
+        }
+        Ok(())
+    }
+
+    /// The `EGLDisplay` of an EGL display, `None` on other backends.
+    pub fn raw_display_handle_egl(&self) -> Option<*const c_void> {
+        egl::raw_display(&self.display)
+    }
+
+    /// The platform handle of context `id`, `None` while it is checked out, in
+    /// which case the checked out context has it.
+    pub fn raw_context_handle(&self, id: ContextId) -> Option<RawContext> {
+        let context = match id {
+            ContextId::Window => self.window.as_ref()?,
+            ContextId::Headless => self.headless.as_ref()?,
+        };
+        Some(context.raw_context())
+    }
+
+    /// Loads the GL or EGL entry point `name` through the display, for
+    /// extension functions glow does not wrap. `None` if the name has a nul
+    /// byte or the display returns null.
+    ///
+    /// # Safety
+    ///
+    /// `T` must be an `extern "system" fn` pointer type, or an `Option` of
+    /// one, whose signature matches the entry point exactly. A non-null
+    /// pointer does not mean the function works: GLX hands out pointers for
+    /// any name, so check the extension string before calling it, with a
+    /// context current that supports it.
+    pub unsafe fn load_ext_fn<T>(&self, name: &str) -> Option<T> {
+        assert_eq!(
+            std::mem::size_of::<T>(),
+            std::mem::size_of::<*const c_void>(),
+            "{} must be loaded as a function pointer",
+            name
+        );
+        let name = CString::new(name).ok()?;
+        let f = self.display.get_proc_address(name.as_c_str());
+        if f.is_null() {
+            return None;
+        }
+        Some(std::mem::transmute_copy(&f))
+    }
+
+    /// Registers an object to be deleted with the window context current on shutdown.
+    pub fn track_wnd(&mut self, obj: GlObject) {
+        self.window_objects.push(obj);
+    }
+
+    /// Registers an object to be deleted with the headless context current on shutdown.
+    pub fn track_head(&mut self, obj: GlObject) {
+        self.headless_objects.push(obj);
+    }
+
+    /// Stops tracking `obj` in context `id`, for objects deleted early. Returns
+    /// whether it was tracked.
+    pub fn untrack(&mut self, id: ContextId, obj: GlObject) -> bool {
+        let objects = match id {
+            ContextId::Window => &mut self.window_objects,
+            ContextId::Headless => &mut self.headless_objects,
+        };
+        let len = objects.len();
+        objects.retain(|o| *o != obj);
+        objects.len() != len
+    }
+
+    /// Deletes all tracked objects and destroys surfaces, contexts and the
+    /// display in that order.
+    pub fn shutdown(mut self) {
+        self.teardown();
+    }
+
+    /// Objects of a context that cannot be made current are left to the
+    /// driver, which frees them with the context.
+    fn teardown(&mut self) {
+        if !self.window_objects.is_empty() {
+            if let Ok(c) = self.try_ct_wnd() {
+                let gl = self.gl_handle(ContextId::Window);
+                for obj in self.window_objects.drain(..) {
+                    unsafe { obj.delete(&gl) };
+                }
+                let _ = self.put_wnd(c);
+            }
+        }
+        if !self.headless_objects.is_empty() {
+            if let Ok(c) = self.try_ct_head() {
+                let gl = self.gl_handle(ContextId::Headless);
+                for obj in self.headless_objects.drain(..) {
+                    unsafe { obj.delete(&gl) };
+                }
+                let _ = self.put_head(c);
+            }
+        }
+    }
+
+    /// Creates another context sharing with the headless root, which every
+    /// context of the wrapper shares with, so objects are visible across all
+    /// of them. Fails with [`ErrorKind::BadContextState`] while the root is
+    /// checked out.
+    pub fn create_shared_context(&self) -> glutin::error::Result<NotCurrentContext> {
+        let root = self.headless.as_ref().ok_or(ErrorKind::BadContextState)?;
+        let attrs = ContextAttributesBuilder::new().with_sharing(root).build();
+        unsafe { self.display.create_context(&root.config(), &attrs) }
+    }
+
+    /// A context for a worker thread, e.g. to upload textures off the main
+    /// thread, sharing with the root like [`Self::create_shared_context`].
+    /// Unlike the contexts of the wrapper it can be sent to another thread,
+    /// where [`WorkerContext::make_current`] makes it current. Surfaces cannot
+    /// be sent along, so that is surfaceless with
+    /// `EGL_KHR_surfaceless_context` and otherwise, as always on GLX, on a
+    /// pbuffer the worker creates. The pbuffer needs a config sharing with
+    /// the root, without one this fails with [`ErrorKind::NotSupported`].
+    ///
+    /// The worker has to `glFlush` after its uploads, and wait for them to
+    /// finish or hand over a fence, before other contexts may use the objects.
+    pub fn spawn_worker_context(&self) -> glutin::error::Result<WorkerContext> {
+        if egl::has_extension(&self.display, "EGL_KHR_surfaceless_context") {
+            return Ok(WorkerContext {
+                context: self.create_shared_context()?,
+                pbuffer: None,
+            });
+        }
+        let root = self.headless.as_ref().ok_or(ErrorKind::BadContextState)?;
+        let config =
+            find_headless_config(&self.display, &root.config()).ok_or(ErrorKind::NotSupported)?;
+        let attrs = ContextAttributesBuilder::new().with_sharing(root).build();
+        let context = unsafe { self.display.create_context(&config, &attrs)? };
+        Ok(WorkerContext {
+            context,
+            pbuffer: Some((self.display.clone(), config)),
+        })
+    }
+
+    /// Checks that sharing is transitive: a texture created in the window
+    /// context is read back in the headless root and in a third context from
+    /// [`Self::create_shared_context`], which only shares with the root.
+    pub fn check_transitive_sharing(&mut self) -> Result<(), SharingDiagnostic> {
+        const PIXEL: [u8; 4] = [12, 34, 56, 78];
+
+        let c = self.try_ct_wnd()?;
+        let gl = self.gl_handle(ContextId::Window);
+        let texture = unsafe {
+            gl.create_texture().map(|tex| {
+                gl.bind_texture(glow::TEXTURE_2D, Some(tex));
+                gl.tex_image_2d(
+                    glow::TEXTURE_2D,
+                    0,
+                    glow::RGBA8 as i32,
+                    1,
+                    1,
+                    0,
+                    glow::RGBA,
+                    glow::UNSIGNED_BYTE,
+                    Some(&PIXEL),
+                );
+                gl.bind_texture(glow::TEXTURE_2D, None);
+                // make the upload visible to the other contexts
+                gl.finish();
+                tex
+            })
+        };
+        self.put_wnd(c)?;
+        let texture = texture.map_err(|_| SharingDiagnostic::NotShared("window"))?;
+
+        // every read is finished before the texture is deleted, so no error
+        // below can leak it
+        let headless = (|| -> glutin::error::Result<_> {
+            let c = self.try_ct_head()?;
+            let pixel = read_texel(self.gl(ContextId::Headless), texture);
+            self.put_head(c)?;
+            Ok(pixel)
+        })();
+
+        let third = (|| -> glutin::error::Result<_> {
+            let c = self
+                .headless_surface
+                .make_current(self.create_shared_context()?)?;
+            let pixel = read_texel(&load_gl(&c), texture);
+            c.make_not_current()?;
+            Ok(pixel)
+        })();
+
+        let c = self.try_ct_wnd()?;
+        unsafe { gl.delete_texture(texture) };
+        self.put_wnd(c)?;
+
+        match headless {
+            Ok(Some(pixel)) if pixel == PIXEL => {}
+            Ok(_) => return Err(SharingDiagnostic::NotShared("headless")),
+            Err(err) => return Err(SharingDiagnostic::Context(err)),
+        }
+        match third {
+            Ok(Some(pixel)) if pixel == PIXEL => Ok(()),
+            Ok(_) => Err(SharingDiagnostic::NotShared("third")),
+            Err(err) => Err(SharingDiagnostic::Context(err)),
+        }
+    }
+
+    /// Describes the window side of the setup, see [`EffectiveConfig`].
+    /// `renderbuffer_format` is the format the shared target was created with.
+    /// Fails if the window context cannot be made current.
+    pub fn effective_config(
+        &mut self,
+        renderbuffer_format: u32,
+    ) -> glutin::error::Result<EffectiveConfig> {
+        let config = self.configs.0.clone();
+        let c = self.try_ct_wnd()?;
+        let gl = self.gl(ContextId::Window);
+        let caps = GlCaps::query(gl);
+        let (renderer, gl_version) = unsafe {
+            (
+                gl.get_parameter_string(glow::RENDERER),
+                gl.get_parameter_string(glow::VERSION),
+            )
+        };
+        let (core_profile, debug, robust) = context_attributes(gl, &caps);
+        self.put_wnd(c)?;
+
+        Ok(EffectiveConfig {
+            backend: Backend::of_display(&self.display),
+            config_id: config_id(&self.display, &config),
+            color_depth: ColorDepth::of_config(&config),
+            samples: config.num_samples(),
+            depth_size: config.depth_size(),
+            stencil_size: config.stencil_size(),
+            srgb: config.srgb_capable(),
+            swap_interval: self.swap_interval.map(|interval| match interval {
+                SwapInterval::DontWait => 0,
+                SwapInterval::Wait(n) => n.get(),
+            }),
+            renderbuffer_format,
+            renderer,
+            gl_version,
+            gles: caps.is_gles(),
+            version: caps.version(),
+            core_profile,
+            debug,
+            robust,
+        })
+    }
+
+    /// Creates a buffer, a texture and a renderbuffer with known content in
+    /// the headless context and checks which of them the window context can
+    /// read. The spec only requires buffers and textures to be shared, some
+    /// drivers leave out renderbuffers. Buffers are reported as not shared
+    /// where the window context cannot map them to read them back. Neither
+    /// context may be current.
+    pub fn probe_sharing_capabilities(&mut self) -> glutin::error::Result<SharingCaps> {
+        const PIXEL: [u8; 4] = [12, 34, 56, 78];
+
+        let c = self.try_ct_head()?;
+        let gl = self.gl_handle(ContextId::Headless);
+        let objects = unsafe {
+            let buffer = gl.create_buffer().ok().map(|buffer| {
+                gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+                gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, &PIXEL, glow::STATIC_READ);
+                gl.bind_buffer(glow::ARRAY_BUFFER, None);
+                buffer
+            });
+            let texture = gl.create_texture().ok().map(|tex| {
+                gl.bind_texture(glow::TEXTURE_2D, Some(tex));
+                gl.tex_image_2d(
+                    glow::TEXTURE_2D,
+                    0,
+                    glow::RGBA8 as i32,
+                    1,
+                    1,
+                    0,
+                    glow::RGBA,
+                    glow::UNSIGNED_BYTE,
+                    Some(&PIXEL),
+                );
+                gl.bind_texture(glow::TEXTURE_2D, None);
+                tex
+            });
+            let renderbuffer = gl.create_renderbuffer().ok().map(|rb| {
+                gl.bind_renderbuffer(glow::RENDERBUFFER, Some(rb));
+                gl.renderbuffer_storage(glow::RENDERBUFFER, glow::RGBA8, 1, 1);
+                gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+                with_renderbuffer_fb(&gl, rb, || {
+                    let [r, g, b, a] = PIXEL.map(|c| c as f32 / 255.0);
+                    gl.clear_color(r, g, b, a);
+                    gl.clear(glow::COLOR_BUFFER_BIT);
+                });
+                rb
+            });
+            // make the content visible to the window context
+            gl.finish();
+            (buffer, texture, renderbuffer)
+        };
+        self.put_head(c)?;
+        let (buffer, texture, renderbuffer) = objects;
+
+        // the objects are deleted even if the window context fails
+        let caps = self.try_ct_wnd().and_then(|c| {
+            let glw = self.gl(ContextId::Window);
+            let can_map = GlCaps::query(glw).has_map_buffer_range();
+            let caps = unsafe {
+                SharingCaps {
+                    buffer: buffer
+                        .filter(|_| can_map)
+                        .and_then(|buffer| read_buffer_head(glw, buffer))
+                        == Some(PIXEL),
+                    texture: texture.and_then(|tex| read_texel(glw, tex)) == Some(PIXEL),
+                    renderbuffer: renderbuffer
+                        .and_then(|rb| with_renderbuffer_fb(glw, rb, || read_pixel(glw)))
+                        == Some(PIXEL),
+                }
+            };
+            self.put_wnd(c).map(|()| caps)
+        });
+
+        let c = self.try_ct_head()?;
+        unsafe {
+            if let Some(buffer) = buffer {
+                gl.delete_buffer(buffer);
+            }
+            if let Some(tex) = texture {
+                gl.delete_texture(tex);
+            }
+            if let Some(rb) = renderbuffer {
+                gl.delete_renderbuffer(rb);
+            }
+        }
+        self.put_head(c)?;
+        caps
+    }
+
+    /// Checks that both contexts can share objects before relying on it:
+    /// the configs need the same client API and color buffer type, should be
+    /// the same config where the platform reports ids, and the contexts have
+    /// to run on the same renderer. Makes each context current once.
+    pub fn validate_sharing(&mut self) -> Result<(), SharingDiagnostic> {
+        check_config_compat(&self.configs.0, &self.configs.1)?;
+        if let (Some(window), Some(headless)) = self.config_ids {
+            if window != headless {
+                return Err(SharingDiagnostic::ConfigIdMismatch { window, headless });
+            }
+        }
+
+        let c = self.try_ct_wnd()?;
+        let wnd_renderer = renderer_string(self.gl(ContextId::Window));
+        self.put_wnd(c)?;
+
+        let c = self.try_ct_head()?;
+        let head_renderer = renderer_string(self.gl(ContextId::Headless));
+        self.put_head(c)?;
+
+        if wnd_renderer != head_renderer {
+            return Err(SharingDiagnostic::RendererMismatch {
+                window: wnd_renderer,
+                headless: head_renderer,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for ContextWrapper {
+    fn drop(&mut self) {
+        self.teardown();
+    }
+}
+
+/// Whether the driver has `context` current on the calling thread, `None` on
+/// backends that cannot be asked.
+fn driver_is_current(display: &Display, context: &RawContext) -> Option<bool> {
+    match *context {
+        RawContext::Egl(ctx) => egl::current_context(display).map(|current| current == ctx),
+        #[cfg(target_os = "linux")]
+        RawContext::Glx(ctx) => {
+            crate::x11::glx_current_context(display).map(|current| current == ctx)
+        }
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+/// Checks whether contexts created from `a` and `b` can legally share objects.
+pub(crate) fn check_config_compat(a: &Config, b: &Config) -> Result<(), SharingDiagnostic> {
+    if a.api() != b.api() {
+        return Err(SharingDiagnostic::ApiMismatch);
+    }
+    let same_buffer_kind = matches!(
+        (a.color_buffer_type(), b.color_buffer_type()),
+        (
+            Some(ColorBufferType::Rgb { .. }),
+            Some(ColorBufferType::Rgb { .. })
+        ) | (
+            Some(ColorBufferType::Luminance(_)),
+            Some(ColorBufferType::Luminance(_))
+        ) | (None, None)
+    );
+    if !same_buffer_kind {
+        return Err(SharingDiagnostic::ConfigMismatch);
+    }
+    Ok(())
+}
+
+type EglSetDamageRegionKhr = unsafe extern "C" fn(
+    dpy: *const c_void,
+    surface: *const c_void,
+    rects: *mut i32,
+    n_rects: i32,
+) -> u32;
+
+#[derive(Debug)]
+pub enum PartialUpdateError {
+    Unsupported,
+    Failed,
+}
+
+impl fmt::Display for PartialUpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartialUpdateError::Unsupported => write!(f, "EGL_KHR_partial_update is not supported"),
+            PartialUpdateError::Failed => write!(f, "eglSetDamageRegionKHR failed"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CreateError {
+    Incompatible(SharingDiagnostic),
+    /// `eglBindAPI` rejected the requested API.
+    BindApi(ContextApi),
+    Context(glutin::error::Error),
+    Surface(glutin::error::Error),
+    /// A surface was requested with a zero width or height.
+    ZeroSize,
+    /// [`ContextOptions`] requested flags that exclude each other, see
+    /// [`ContextOptions::conflicts`]. Nothing was sent to the driver.
+    ConflictingFlags(Vec<(&'static str, &'static str)>),
+    /// The driver did not grant the [`GlVersionRequest`], `granted` is what
+    /// it gave instead.
+    Version {
+        requested: GlVersionRequest,
+        granted: (u32, u32),
+    },
+    /// GLX only offered an indirect context, see
+    /// [`ContextWrapper::is_direct_rendering`].
+    IndirectRendering,
+}
+
+impl fmt::Display for CreateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreateError::Incompatible(diag) => write!(f, "configs cannot share: {}", diag),
+            CreateError::BindApi(api) => write!(f, "failed to bind client api {:?}", api),
+            CreateError::Context(err) => write!(f, "failed to create context: {}", err),
+            CreateError::Surface(err) => write!(f, "failed to create surface: {}", err),
+            CreateError::ZeroSize => write!(f, "surfaces cannot have a zero size"),
+            CreateError::ConflictingFlags(conflicts) => {
+                write!(f, "conflicting context flags:")?;
+                for (i, (a, b)) in conflicts.iter().enumerate() {
+                    let sep = if i == 0 { " " } else { ", " };
+                    write!(f, "{}{} with {}", sep, a, b)?;
+                }
+                Ok(())
+            }
+            CreateError::Version { requested, granted } => write!(
+                f,
+                "requested gl version {:?}, got {}.{}",
+                requested, granted.0, granted.1
+            ),
+            CreateError::IndirectRendering => write!(
+                f,
+                "glx only offers indirect rendering, which lacks framebuffer objects and \
+                 blits (e.g. over ssh X forwarding); use EGL or a local display"
+            ),
+        }
+    }
+}
+
+impl From<glutin::error::Error> for CreateError {
+    fn from(err: glutin::error::Error) -> Self {
+        CreateError::Context(err)
+    }
+}
+
+/// Why creating or changing GL objects through a [`ContextWrapper`] failed.
+#[derive(Debug)]
+pub enum ObjectError {
+    /// Making a context current failed, see [`ContextWrapper::try_ct_wnd`].
+    Context(glutin::error::Error),
+    /// Creating an object failed or the request does not fit the object.
+    Gl(String),
+}
+
+impl fmt::Display for ObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjectError::Context(err) => write!(f, "context error: {}", err),
+            ObjectError::Gl(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<glutin::error::Error> for ObjectError {
+    fn from(err: glutin::error::Error) -> Self {
+        ObjectError::Context(err)
+    }
+}
+
+impl From<String> for ObjectError {
+    fn from(err: String) -> Self {
+        ObjectError::Gl(err)
+    }
+}
+
+/// Attributes both contexts of [`create_shared_contexts`] are created with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ContextOptions {
+    /// OpenGL or OpenGL ES, the driver picks if `None`.
+    pub api: Option<ContextApi>,
+    /// Requests a context without deprecated functionality, which requires a
+    /// core profile. glutin has no separate switch for
+    /// `GL_CONTEXT_FLAG_FORWARD_COMPATIBLE_BIT`, so this requests the core
+    /// profile, which is forward compatible on macOS and wherever the backend
+    /// sets the bit for it. Check
+    /// [`GlCaps::forward_compatible`](crate::GlCaps::forward_compatible) for
+    /// what was granted. Only meaningful for desktop GL 3.0 and later.
+    pub forward_compatible: bool,
+    pub version: GlVersionRequest,
+    /// Requests a debug context, which reports through `KHR_debug`.
+    pub debug: bool,
+    /// Requests a context that skips error checking
+    /// (`EGL_KHR_create_context_no_error`), undefined behavior on any error.
+    pub no_error: bool,
+    /// Requests a robust context that reports resets as a lost context, which
+    /// the app recovers from.
+    pub robust: bool,
+}
+
+/// Which GL version [`create_shared_contexts`] asks for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GlVersionRequest {
+    /// Whatever the driver creates by default, usually its highest version.
+    /// [`GlCaps::version`](crate::GlCaps::version) reports what it is.
+    #[default]
+    Highest,
+    /// Exactly this version, failing with [`CreateError::Version`] if the
+    /// driver grants another one.
+    Exact(u32, u32),
+    /// The driver default if it is at least this version, otherwise a context
+    /// of exactly this version. Some drivers default to a legacy version
+    /// below what they support when asked explicitly.
+    AtLeast(u32, u32),
+}
+
+impl GlVersionRequest {
+    fn accepts(self, (major, minor): (u32, u32)) -> bool {
+        match self {
+            GlVersionRequest::Highest => true,
+            GlVersionRequest::Exact(maj, min) => (major, minor) == (maj, min),
+            GlVersionRequest::AtLeast(maj, min) => (major, minor) >= (maj, min),
+        }
+    }
+
+    /// The explicit version to request, if any.
+    fn explicit(self) -> Option<Version> {
+        match self {
+            GlVersionRequest::Highest => None,
+            GlVersionRequest::Exact(maj, min) | GlVersionRequest::AtLeast(maj, min) => {
+                Some(Version::new(maj as u8, min as u8))
+            }
+        }
+    }
+}
+
+impl ContextOptions {
+    /// Requests `version`, or leaves the version to the driver if `None`, on
+    /// top of the options.
+    fn apply_version(
+        self,
+        builder: ContextAttributesBuilder,
+        version: Option<Version>,
+    ) -> ContextAttributesBuilder {
+        match (self.api, version) {
+            (Some(ContextApi::Gles(_)), Some(v)) => self
+                .apply(builder)
+                .with_context_api(ContextApi::Gles(Some(v))),
+            (_, Some(v)) => self
+                .apply(builder)
+                .with_context_api(ContextApi::OpenGl(Some(v))),
+            (_, None) => self.apply(builder),
+        }
+    }
+
+    /// The pairs of requested flags that exclude each other: a no-error
+    /// context cannot report errors to a debug callback nor detect resets.
+    pub fn conflicts(&self) -> Vec<(&'static str, &'static str)> {
+        let mut conflicts = Vec::new();
+        if self.no_error && self.debug {
+            conflicts.push(("no_error", "debug"));
+        }
+        if self.no_error && self.robust {
+            conflicts.push(("no_error", "robust"));
+        }
+        conflicts
+    }
+
+    fn apply(self, mut builder: ContextAttributesBuilder) -> ContextAttributesBuilder {
+        if let Some(api) = self.api {
+            builder = builder.with_context_api(api);
+        }
+        if self.forward_compatible {
+            builder = builder.with_profile(GlProfile::Core);
+        }
+        if self.debug {
+            builder = builder.with_debug(true);
+        }
+        if self.robust {
+            builder = builder.with_robustness(Robustness::RobustLoseContextOnReset);
+        } else if self.no_error {
+            builder = builder.with_robustness(Robustness::NoError);
+        }
+        builder
+    }
+}
+
+/// Creates the headless root context and a windowed context sharing with it,
+/// returned as `(windowed, headless)`.
+///
+/// The headless context uses `headless_config` if given, which must be
+/// sharing compatible with `config` and match `headless_surface`. This allows
+/// a leaner config without multisampling or depth for the offscreen side.
+///
+/// On GLX the root is made current on `headless_surface` once before the
+/// sharing context is created, some NVIDIA drivers fail to create it otherwise.
+/// An indirect GLX context fails with [`CreateError::IndirectRendering`].
+///
+/// Both contexts are created with the same `options`. With an api set, on EGL
+/// the matching client API is bound first, as EGL defaults to whichever API
+/// the implementation prefers and fails to create contexts of the other.
+pub fn create_shared_contexts(
+    display: &Display,
+    config: &Config,
+    headless_config: Option<&Config>,
+    raw_wnd: RawWindowHandle,
+    headless_surface: &AnySurface,
+    options: ContextOptions,
+) -> Result<(NotCurrentContext, NotCurrentContext), CreateError> {
+    let headless_config = headless_config.unwrap_or(config);
+    check_config_compat(config, headless_config).map_err(CreateError::Incompatible)?;
+    let conflicts = options.conflicts();
+    if !conflicts.is_empty() {
+        return Err(CreateError::ConflictingFlags(conflicts));
+    }
+
+    if let Some(api) = options.api {
+        if !egl::bind_api(display, api) {
+            return Err(CreateError::BindApi(api));
+        }
+    }
+
+    // only an exact request is passed on right away, see `GlVersionRequest::AtLeast`
+    let mut version = match options.version {
+        GlVersionRequest::Exact(..) => options.version.explicit(),
+        _ => None,
+    };
+    let headless = loop {
+        let attributes = options.apply_version(ContextAttributesBuilder::new(), version);
+        let headless = unsafe { display.create_context(headless_config, &attributes.build())? };
+        if options.version == GlVersionRequest::Highest {
+            if let Display::Glx(_) = display {
+                break headless_surface
+                    .make_current(headless)?
+                    .make_not_current()?;
+            }
+            break headless;
+        }
+
+        let c = headless_surface.make_current(headless)?;
+        let granted = {
+            let gl = load_gl(&c);
+            (gl.version().major, gl.version().minor)
+        };
+        let headless = c.make_not_current()?;
+        if options.version.accepts(granted) {
+            break headless;
+        }
+        if version.is_some() {
+            return Err(CreateError::Version {
+                requested: options.version,
+                granted,
+            });
+        }
+        version = options.version.explicit();
+    };
+    // indirect GLX fails on the first framebuffer object with no useful error
+    #[cfg(target_os = "linux")]
+    if crate::x11::glx_is_direct(display, headless.raw_context()) == Some(false) {
+        return Err(CreateError::IndirectRendering);
+    }
+    let attributes = || options.apply_version(ContextAttributesBuilder::new(), version);
+
+    let windowed = unsafe {
+        display.create_context(
+            config,
+            &attributes().with_sharing(&headless).build_windowed(raw_wnd),
+        )?
+    };
+    // surface GLX errors of the creation here rather than on a later call
+    #[cfg(target_os = "linux")]
+    crate::x11::pump_errors(display);
+
+    Ok((windowed, headless))
+}
+
+#[derive(Debug)]
+pub enum SharingDiagnostic {
+    ApiMismatch,
+    ConfigMismatch,
+    /// The contexts were created from different configs, holds their
+    /// [`config_id`]s.
+    ConfigIdMismatch {
+        window: i32,
+        headless: i32,
+    },
+    RendererMismatch {
+        window: String,
+        headless: String,
+    },
+    /// An object created in another context, a texture for the sharing checks
+    /// of the wrapper, could not be read back in the named context.
+    NotShared(&'static str),
+    /// The object is smaller than the pattern the check writes, holds its
+    /// size in bytes.
+    TooSmall(usize),
+    Context(glutin::error::Error),
+}
+
+impl fmt::Display for SharingDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SharingDiagnostic::ApiMismatch => write!(
+                f,
+                "contexts were created for different client APIs and cannot share objects"
+            ),
+            SharingDiagnostic::ConfigMismatch => {
+                write!(f, "contexts use configs with different color buffer types")
+            }
+            SharingDiagnostic::ConfigIdMismatch { window, headless } => write!(
+                f,
+                "contexts use different configs (window: {}, headless: {})",
+                window, headless
+            ),
+            SharingDiagnostic::RendererMismatch { window, headless } => write!(
+                f,
+                "contexts run on different renderers (window: {}, headless: {}), \
+                 they are likely on different GPUs",
+                window, headless
+            ),
+            SharingDiagnostic::NotShared(context) => write!(
+                f,
+                "an object shared with the {} context is not visible in it",
+                context
+            ),
+            SharingDiagnostic::TooSmall(size) => write!(
+                f,
+                "a {} byte object is too small to check for sharing",
+                size
+            ),
+            SharingDiagnostic::Context(err) => write!(f, "context error: {}", err),
+        }
+    }
+}
+
+impl From<glutin::error::Error> for SharingDiagnostic {
+    fn from(err: glutin::error::Error) -> Self {
+        SharingDiagnostic::Context(err)
+    }
+}
+
+/// Takes every pending error flag of the current context, `glGetError`
+/// returns one flag per call until all are cleared.
+pub fn drain_gl_errors(gl: &glow::Context) -> Vec<u32> {
+    let mut errors = Vec::new();
+    loop {
+        let err = unsafe { gl.get_error() };
+        // a lost context keeps reporting CONTEXT_LOST
+        if err == glow::NO_ERROR || errors.contains(&err) {
+            break;
+        }
+        errors.push(err);
+    }
+    errors
+}
+
+pub fn load_gl(c: &PossiblyCurrentContext) -> glow::Context {
+    load_gl_tracked(c).0
+}
+
+/// Like [`load_gl`], but also returns the symbols the context returned null
+/// for. glow leaves those unset, calling one of them will crash.
+pub fn load_gl_tracked(c: &PossiblyCurrentContext) -> (glow::Context, Vec<String>) {
+    let mut missing = Vec::new();
+    let gl = unsafe {
+        glow::Context::from_loader_function(|s| {
+            let f = match CString::new(s) {
+                Ok(name) => c.get_proc_address(name.as_c_str()),
+                Err(_) => std::ptr::null(),
+            };
+            if f.is_null() {
+                missing.push(s.to_owned());
+            }
+            f.cast()
+        })
+    };
+    (gl, missing)
+}
+
+/// Which object types [`ContextWrapper::probe_sharing_capabilities`] found
+/// to be shared between the contexts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SharingCaps {
+    pub buffer: bool,
+    pub texture: bool,
+    pub renderbuffer: bool,
+}
+
+impl SharingCaps {
+    /// The object to present through, preferring `preferred` if it is shared.
+    /// `None` if neither is, so nothing rendered offscreen would show up.
+    pub fn share_object(self, preferred: ShareObject) -> Option<ShareObject> {
+        match (preferred, self.renderbuffer, self.texture) {
+            (ShareObject::Renderbuffer, true, _) => Some(ShareObject::Renderbuffer),
+            (_, _, true) => Some(ShareObject::Texture),
+            (_, true, false) => Some(ShareObject::Renderbuffer),
+            (_, false, false) => None,
+        }
+    }
+}
+
+/// Runs `f` with a temporary framebuffer bound that has `rb` attached, `None`
+/// if the framebuffer is incomplete.
+unsafe fn with_renderbuffer_fb<T>(
+    gl: &glow::Context,
+    rb: glow::NativeRenderbuffer,
+    f: impl FnOnce() -> T,
+) -> Option<T> {
+    let fb = gl.create_framebuffer().ok()?;
+    gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+    gl.framebuffer_renderbuffer(
+        glow::FRAMEBUFFER,
+        glow::COLOR_ATTACHMENT0,
+        glow::RENDERBUFFER,
+        Some(rb),
+    );
+    let complete = gl.check_framebuffer_status(glow::FRAMEBUFFER) == glow::FRAMEBUFFER_COMPLETE;
+    let result = complete.then(f);
+    gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+    gl.delete_framebuffer(fb);
+    result
+}
+
+/// Reads the bottom left pixel of the bound framebuffer.
+unsafe fn read_pixel(gl: &glow::Context) -> [u8; 4] {
+    let mut pixel = [0; 4];
+    gl.read_pixels(
+        0,
+        0,
+        1,
+        1,
+        glow::RGBA,
+        glow::UNSIGNED_BYTE,
+        glow::PixelPackData::Slice(&mut pixel),
+    );
+    pixel
+}
+
+/// Reads the first four bytes of `buffer` by mapping it, which needs
+/// [`GlCaps::has_map_buffer_range`].
+unsafe fn read_buffer_head(gl: &glow::Context, buffer: glow::NativeBuffer) -> Option<[u8; 4]> {
+    let mut head = [0; 4];
+    gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+    let ptr = gl.map_buffer_range(glow::ARRAY_BUFFER, 0, 4, glow::MAP_READ_BIT);
+    let mapped = !ptr.is_null();
+    if mapped {
+        std::ptr::copy_nonoverlapping(ptr, head.as_mut_ptr(), head.len());
+        gl.unmap_buffer(glow::ARRAY_BUFFER);
+    }
+    gl.bind_buffer(glow::ARRAY_BUFFER, None);
+    mapped.then_some(head)
+}
+
+/// Reads the first texel of `texture` through a temporary framebuffer in the
+/// current context.
+fn read_texel(gl: &glow::Context, texture: glow::NativeTexture) -> Option<[u8; 4]> {
+    unsafe {
+        let fb = gl.create_framebuffer().ok()?;
+        gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+        gl.framebuffer_texture_2d(
+            glow::FRAMEBUFFER,
+            glow::COLOR_ATTACHMENT0,
+            glow::TEXTURE_2D,
+            Some(texture),
+            0,
+        );
+        let complete = gl.check_framebuffer_status(glow::FRAMEBUFFER) == glow::FRAMEBUFFER_COMPLETE;
+        let pixel = complete.then(|| read_pixel(gl));
+        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+        gl.delete_framebuffer(fb);
+        pixel
+    }
+}
+
+fn renderer_string(gl: &glow::Context) -> String {
+    unsafe { gl.get_parameter_string(glow::RENDERER) }
+}
+
+/// Whether the current context is `(core profile, debug, robust)`, as far as
+/// its version and extensions allow asking.
+fn context_attributes(gl: &glow::Context, caps: &GlCaps) -> (bool, bool, bool) {
+    let at_least = |version| caps.version() >= version;
+    let gles = caps.is_gles();
+    unsafe {
+        let core_profile = !gles
+            && at_least((3, 2))
+            && gl.get_parameter_i32(glow::CONTEXT_PROFILE_MASK) as u32
+                & glow::CONTEXT_CORE_PROFILE_BIT
+                != 0;
+        // GL_CONTEXT_FLAGS is desktop GL 3.0+ and GLES 3.2+
+        let has_flags = if gles {
+            at_least((3, 2)) || caps.has_extension("GL_KHR_debug")
+        } else {
+            at_least((3, 0))
+        };
+        let debug = has_flags
+            && gl.get_parameter_i32(glow::CONTEXT_FLAGS) as u32 & glow::CONTEXT_FLAG_DEBUG_BIT != 0;
+        let has_robustness = (if gles {
+            at_least((3, 2))
+        } else {
+            at_least((4, 5))
+        }) || [
+            "GL_ARB_robustness",
+            "GL_KHR_robustness",
+            "GL_EXT_robustness",
+        ]
+        .iter()
+        .any(|ext| caps.has_extension(ext));
+        let robust = has_robustness
+            && gl.get_parameter_i32(glow::RESET_NOTIFICATION_STRATEGY) as u32
+                == glow::LOSE_CONTEXT_ON_RESET;
+        (core_profile, debug, robust)
+    }
+}
@@ -0,0 +1,71 @@
+//! Last known GL state of the calling thread, printed by
+//! [`install_diagnostic_panic_hook`] when that thread panics. The hook cannot
+//! query GL itself, it may run with no context or the wrong one current, so
+//! [`AppBuilder`](crate::AppBuilder) apps record the state as they go and
+//! other code can do the same with the `record_*` functions.
+
+use std::{cell::RefCell, panic};
+
+use crate::display::EffectiveConfig;
+
+#[derive(Default)]
+struct GlState {
+    config: Option<EffectiveConfig>,
+    framebuffer: Option<(&'static str, Option<glow::NativeFramebuffer>)>,
+    error: Option<(&'static str, u32)>,
+}
+
+thread_local! {
+    static STATE: RefCell<GlState> = RefCell::new(GlState::default());
+}
+
+/// Records the config the contexts ended up with, including the renderer.
+pub fn record_config(config: &EffectiveConfig) {
+    STATE.with(|state| state.borrow_mut().config = Some(config.clone()));
+}
+
+/// Records the framebuffer bound last in context `context`, `None` for the
+/// default one.
+pub fn record_framebuffer(context: &'static str, framebuffer: Option<glow::NativeFramebuffer>) {
+    STATE.with(|state| state.borrow_mut().framebuffer = Some((context, framebuffer)));
+}
+
+/// Records a GL error `source` left behind.
+pub fn record_gl_error(source: &'static str, error: u32) {
+    STATE.with(|state| state.borrow_mut().error = Some((source, error)));
+}
+
+/// Chains a hook printing the recorded GL state of the panicking thread after
+/// the previously installed hook ran. Installing it twice prints it twice.
+pub fn install_diagnostic_panic_hook() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        previous(info);
+        // the state may be borrowed by the panicking code or already destroyed
+        let _ = STATE.try_with(|state| {
+            if let Ok(state) = state.try_borrow() {
+                print_state(&state);
+            }
+        });
+    }));
+}
+
+fn print_state(state: &GlState) {
+    eprintln!("last known GL state:");
+    match &state.config {
+        Some(config) => {
+            eprintln!("  renderer: {} ({})", config.renderer, config.gl_version);
+            eprintln!("  config: {:?}", config);
+        }
+        None => eprintln!("  config: unknown"),
+    }
+    match state.framebuffer {
+        Some((context, Some(fb))) => eprintln!("  framebuffer: {:?} in {}", fb, context),
+        Some((context, None)) => eprintln!("  framebuffer: default in {}", context),
+        None => eprintln!("  framebuffer: unknown"),
+    }
+    match state.error {
+        Some((source, error)) => eprintln!("  last GL error: 0x{:04X} from {}", error, source),
+        None => eprintln!("  last GL error: none seen"),
+    }
+}
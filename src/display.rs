@@ -0,0 +1,722 @@
+use std::{
+    ffi::{c_void, CStr},
+    fmt,
+    num::NonZeroU32,
+};
+
+use glutin::{
+    config::{
+        AsRawConfig, ColorBufferType, Config, ConfigSurfaceTypes, ConfigTemplate,
+        ConfigTemplateBuilder, RawConfig,
+    },
+    display::{AsRawDisplay, Display, DisplayApiPreference, DisplayPicker, RawDisplay},
+    prelude::{GlConfig, GlDisplay},
+    surface::{Surface, SurfaceAttributesBuilder, WindowSurface},
+};
+use raw_window_handle::{RawDisplayHandle, RawWindowHandle};
+// the X11 specifics moved out of `platform::unix` in winit 0.28
+#[cfg(feature = "winit-027")]
+use winit::platform::unix::register_xlib_error_hook as xlib_error_hook;
+#[cfg(feature = "winit-028")]
+use winit::platform::x11::register_xlib_error_hook as xlib_error_hook;
+
+use crate::{
+    context::{check_config_compat, CreateError},
+    egl,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorDepth {
+    Srgb8,
+    Rgb10A2,
+    Rgba16F,
+}
+
+impl ColorDepth {
+    /// Internal format of the shared renderbuffer matching this depth.
+    pub fn renderbuffer_format(self) -> u32 {
+        match self {
+            ColorDepth::Srgb8 => glow::RGB8,
+            ColorDepth::Rgb10A2 => glow::RGB10_A2,
+            ColorDepth::Rgba16F => glow::RGBA16F,
+        }
+    }
+
+    /// The depth `config` provides, 8 bit for anything unrecognized.
+    pub fn of_config(config: &Config) -> Self {
+        match config.color_buffer_type() {
+            Some(ColorBufferType::Rgb { r_size: 10, .. }) => ColorDepth::Rgb10A2,
+            Some(ColorBufferType::Rgb { r_size: 16, .. }) => ColorDepth::Rgba16F,
+            _ => ColorDepth::Srgb8,
+        }
+    }
+
+    fn apply(self, template: ConfigTemplateBuilder) -> ConfigTemplateBuilder {
+        match self {
+            ColorDepth::Srgb8 => template,
+            ColorDepth::Rgb10A2 => template
+                .with_buffer_type(ColorBufferType::Rgb {
+                    r_size: 10,
+                    g_size: 10,
+                    b_size: 10,
+                })
+                .with_alpha_size(2),
+            ColorDepth::Rgba16F => template
+                .with_buffer_type(ColorBufferType::Rgb {
+                    r_size: 16,
+                    g_size: 16,
+                    b_size: 16,
+                })
+                .with_alpha_size(16)
+                .with_float_pixels(true),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Backend {
+    Glx,
+    Egl,
+}
+
+impl Backend {
+    pub fn of_display(display: &Display) -> Option<Self> {
+        match display.raw_display() {
+            RawDisplay::Glx(_) => Some(Backend::Glx),
+            RawDisplay::Egl(_) => Some(Backend::Egl),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    /// Opens a display of this backend, failing if it is unavailable.
+    pub fn create_display(self, raw_display: RawDisplayHandle) -> glutin::error::Result<Display> {
+        let preference = match self {
+            Backend::Glx => DisplayApiPreference::Glx,
+            Backend::Egl => DisplayApiPreference::Egl,
+        };
+        unsafe {
+            Display::from_raw(
+                raw_display,
+                DisplayPicker::new()
+                    .with_api_preference(preference)
+                    .with_glx_error_registrar(Box::new(xlib_error_hook)),
+            )
+        }
+    }
+}
+
+/// Everything that determines how a setup renders, for attaching to bug
+/// reports and recreating the setup with [`select_display_config_from`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EffectiveConfig {
+    /// `None` if the display is of a backend the crate does not know.
+    pub backend: Option<Backend>,
+    /// [`config_id`] of the window config, only meaningful on the same machine.
+    pub config_id: Option<i32>,
+    pub color_depth: ColorDepth,
+    pub samples: u8,
+    pub depth_size: u8,
+    pub stencil_size: u8,
+    pub srgb: bool,
+    /// Last swap interval set, 0 for no vsync, `None` for the driver default.
+    pub swap_interval: Option<u32>,
+    /// Internal format of the shared target.
+    pub renderbuffer_format: u32,
+    pub renderer: String,
+    pub gl_version: String,
+    /// Client API of the window context, OpenGL ES rather than desktop GL.
+    pub gles: bool,
+    /// Context version, as parsed from [`Self::gl_version`].
+    pub version: (u32, u32),
+    /// Core profile context, always `false` on GLES and before GL 3.2.
+    pub core_profile: bool,
+    /// Debug context, reporting through `KHR_debug`.
+    pub debug: bool,
+    /// Robust context reporting resets as a lost context.
+    pub robust: bool,
+}
+
+pub struct DisplayConfig {
+    pub display: Display,
+    pub config: Config,
+    /// The backend the display was created with.
+    pub backend: Backend,
+    /// The color depth that was actually granted.
+    pub color_depth: ColorDepth,
+}
+
+impl DisplayConfig {
+    /// Samples per pixel of the selected config, 0 if it is not multisampled.
+    pub fn granted_samples(&self) -> u8 {
+        self.config.num_samples()
+    }
+
+    pub fn granted_depth_size(&self) -> u8 {
+        self.config.depth_size()
+    }
+
+    pub fn granted_stencil_size(&self) -> u8 {
+        self.config.stencil_size()
+    }
+}
+
+/// Sample counts tried for a request of `samples`: the request itself, then
+/// each lower power of two down to no multisampling, e.g. `8, 4, 2, 0`.
+pub fn sample_steps(samples: u8) -> Vec<u8> {
+    let mut steps = vec![samples];
+    let mut n = 128;
+    while n >= 2 {
+        if n < samples {
+            steps.push(n);
+        }
+        n /= 2;
+    }
+    if samples != 0 {
+        steps.push(0);
+    }
+    steps
+}
+
+/// Picks a display and a config with the requested color depth and sample
+/// count. The sample count steps down through [`sample_steps`] before the
+/// color depth falls back to 8 bit per channel.
+///
+/// With `depth_stencil` set, the config with the fewest depth and stencil bits
+/// at or above the requested sizes is picked, so `Some((0, 0))` prefers
+/// configs without any over ones the driver pads. With `None` the first
+/// matching config is used whatever its depth and stencil sizes.
+pub fn select_display_config(
+    raw_display: RawDisplayHandle,
+    raw_wnd: RawWindowHandle,
+    color_depth: ColorDepth,
+    samples: u8,
+    depth_stencil: Option<(u8, u8)>,
+) -> Result<DisplayConfig, PickError> {
+    // first try glx, then egl
+    let mut backend = Backend::Glx;
+    let mut display = backend.create_display(raw_display);
+    if display.is_err() {
+        backend = Backend::Egl;
+        display = backend.create_display(raw_display);
+    }
+    let display = display.map_err(PickError::NoDisplay)?;
+    select_config(
+        display,
+        backend,
+        raw_wnd,
+        color_depth,
+        samples,
+        depth_stencil,
+    )
+}
+
+/// Recreates the setup described by `effective`: the same backend if available
+/// and the config with the same id if it still has the same attributes,
+/// otherwise the closest config [`select_display_config`] finds.
+pub fn select_display_config_from(
+    raw_display: RawDisplayHandle,
+    raw_wnd: RawWindowHandle,
+    effective: &EffectiveConfig,
+) -> Result<DisplayConfig, PickError> {
+    let samples = effective.samples;
+    let depth_stencil = Some((effective.depth_size, effective.stencil_size));
+    let backend_display = effective
+        .backend
+        .map(|backend| (backend, backend.create_display(raw_display)));
+    let (backend, display) = match backend_display {
+        Some((backend, Ok(display))) => (backend, display),
+        _ => {
+            return select_display_config(
+                raw_display,
+                raw_wnd,
+                effective.color_depth,
+                samples,
+                depth_stencil,
+            )
+        }
+    };
+
+    let same = effective
+        .config_id
+        .and_then(|id| config_by_id(&display, raw_wnd, id))
+        .filter(|config| {
+            ColorDepth::of_config(config) == effective.color_depth
+                && config.num_samples() == samples
+                && config.depth_size() == effective.depth_size
+                && config.stencil_size() == effective.stencil_size
+        });
+    match same {
+        Some(config) => Ok(DisplayConfig {
+            display,
+            config,
+            backend,
+            color_depth: effective.color_depth,
+        }),
+        None => select_config(
+            display,
+            backend,
+            raw_wnd,
+            effective.color_depth,
+            samples,
+            depth_stencil,
+        ),
+    }
+}
+
+fn select_config(
+    display: Display,
+    backend: Backend,
+    raw_wnd: RawWindowHandle,
+    color_depth: ColorDepth,
+    samples: u8,
+    depth_stencil: Option<(u8, u8)>,
+) -> Result<DisplayConfig, PickError> {
+    let find_config = |depth: ColorDepth| -> Result<Option<Config>, PickError> {
+        for samples in sample_steps(samples) {
+            let mut template = ConfigTemplateBuilder::new()
+                .compatible_with_native_window(raw_wnd)
+                .with_surface_type(ConfigSurfaceTypes::WINDOW);
+            if samples > 0 {
+                template = template.with_multisampling(samples);
+            }
+            if let Some((depth_size, stencil_size)) = depth_stencil {
+                template = template
+                    .with_depth_size(depth_size)
+                    .with_stencil_size(stencil_size);
+            }
+            let mut configs = unsafe { display.find_configs(depth.apply(template).build())? };
+            let config = match depth_stencil {
+                Some(_) => {
+                    configs.min_by_key(|config| (config.depth_size(), config.stencil_size()))
+                }
+                None => configs.next(),
+            };
+            if config.is_some() {
+                return Ok(config);
+            }
+        }
+        Ok(None)
+    };
+
+    let (config, color_depth) = match find_config(color_depth)? {
+        Some(config) => (config, color_depth),
+        None => (
+            find_config(ColorDepth::Srgb8)?.ok_or(PickError::NoConfig)?,
+            ColorDepth::Srgb8,
+        ),
+    };
+
+    Ok(DisplayConfig {
+        display,
+        config,
+        backend,
+        color_depth,
+    })
+}
+
+/// Finds the pbuffer capable config with the fewest samples, depth and stencil
+/// bits that can still share with `window_config`.
+pub fn find_headless_config(display: &Display, window_config: &Config) -> Option<Config> {
+    let template = ConfigTemplateBuilder::new()
+        .with_surface_type(ConfigSurfaceTypes::PBUFFER)
+        .build();
+    unsafe { display.find_configs(template) }
+        .ok()?
+        .filter(|config| check_config_compat(window_config, config).is_ok())
+        .min_by_key(|config| {
+            (
+                config.num_samples(),
+                config.depth_size(),
+                config.stencil_size(),
+            )
+        })
+}
+
+const EGL_CONFIG_ID: i32 = 0x3028;
+const EGL_NATIVE_VISUAL_ID: i32 = 0x302E;
+const EGL_MIN_SWAP_INTERVAL: i32 = 0x303B;
+const EGL_MAX_SWAP_INTERVAL: i32 = 0x303C;
+const GLX_FBCONFIG_ID: i32 = 0x8013;
+const GLX_VISUAL_ID: i32 = 0x800B;
+
+type GetConfigAttrib = unsafe extern "C" fn(
+    dpy: *const c_void,
+    config: *const c_void,
+    attrib: i32,
+    value: *mut i32,
+) -> i32;
+
+/// Native id of `config` (`EGL_CONFIG_ID` or `GLX_FBCONFIG_ID`). Unlike the
+/// order of `find_configs` it is stable across runs on the same machine.
+pub fn config_id(display: &Display, config: &Config) -> Option<i32> {
+    config_attrib(display, config, EGL_CONFIG_ID, GLX_FBCONFIG_ID)
+}
+
+/// `EGL_MIN_SWAP_INTERVAL` and `EGL_MAX_SWAP_INTERVAL` of `config`, `None`
+/// on GLX, whose configs have no such attributes.
+pub(crate) fn swap_interval_range(display: &Display, config: &Config) -> Option<(u32, u32)> {
+    if !matches!(display.raw_display(), RawDisplay::Egl(_)) {
+        return None;
+    }
+    let min = config_attrib(display, config, EGL_MIN_SWAP_INTERVAL, 0)?;
+    let max = config_attrib(display, config, EGL_MAX_SWAP_INTERVAL, 0)?;
+    Some((min.max(0) as u32, max.max(0) as u32))
+}
+
+/// X visual id of `config`, 0 or `None` if it has none.
+pub fn native_visual_id(display: &Display, config: &Config) -> Option<i32> {
+    config_attrib(display, config, EGL_NATIVE_VISUAL_ID, GLX_VISUAL_ID)
+}
+
+fn config_attrib(
+    display: &Display,
+    config: &Config,
+    egl_attrib: i32,
+    glx_attrib: i32,
+) -> Option<i32> {
+    let (dpy, name, attrib, success) = match display.raw_display() {
+        RawDisplay::Egl(dpy) => (dpy, &b"eglGetConfigAttrib\0"[..], egl_attrib, 1),
+        RawDisplay::Glx(dpy) => (dpy, &b"glXGetFBConfigAttrib\0"[..], glx_attrib, 0),
+        _ => return None,
+    };
+    let raw_config = match config.raw_config() {
+        RawConfig::Egl(config) | RawConfig::Glx(config) => config,
+        #[allow(unreachable_patterns)]
+        _ => return None,
+    };
+
+    let f = display.get_proc_address(CStr::from_bytes_with_nul(name).ok()?);
+    if f.is_null() {
+        return None;
+    }
+    let get_config_attrib: GetConfigAttrib = unsafe { std::mem::transmute(f) };
+
+    let mut value = 0;
+    if unsafe { get_config_attrib(dpy, raw_config, attrib, &mut value) } != success {
+        return None;
+    }
+    Some(value)
+}
+
+/// Looks up the window compatible config with the given [`config_id`].
+pub fn config_by_id(display: &Display, raw_wnd: RawWindowHandle, id: i32) -> Option<Config> {
+    let template = ConfigTemplateBuilder::new()
+        .compatible_with_native_window(raw_wnd)
+        .with_surface_type(ConfigSurfaceTypes::WINDOW)
+        .build();
+    unsafe { display.find_configs(template) }
+        .ok()?
+        .find(|config| config_id(display, config) == Some(id))
+}
+
+#[derive(Debug)]
+pub enum PickError {
+    /// Neither backend could open a display, with the error of the last one.
+    NoDisplay(glutin::error::Error),
+    Find(glutin::error::Error),
+    NoConfig,
+    /// Configs matched the template, but none has the visual of the window.
+    NoVisualMatch(u32),
+}
+
+impl fmt::Display for PickError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PickError::NoDisplay(err) => write!(f, "no display backend found: {}", err),
+            PickError::Find(err) => write!(f, "failed to find configs: {}", err),
+            PickError::NoConfig => write!(f, "no config matches the template"),
+            PickError::NoVisualMatch(visual) => {
+                write!(f, "no config matches the window visual {:#x}", visual)
+            }
+        }
+    }
+}
+
+impl From<glutin::error::Error> for PickError {
+    fn from(err: glutin::error::Error) -> Self {
+        PickError::Find(err)
+    }
+}
+
+/// Visual the window was created with, `None` if it is not an X11 window or
+/// was created without a specific visual.
+fn window_visual(raw_wnd: RawWindowHandle) -> Option<u32> {
+    let visual = match raw_wnd {
+        RawWindowHandle::Xlib(handle) => handle.visual_id as u32,
+        RawWindowHandle::Xcb(handle) => handle.visual_id,
+        _ => return None,
+    };
+    (visual != 0).then_some(visual)
+}
+
+/// Picks the best config for `template` that can render to `raw_wnd`. On X11
+/// configs whose native visual differs from the window's are dropped, since
+/// presenting through them gives a black window on some drivers. The rest are
+/// ranked by most samples, then fewest depth and stencil bits.
+pub fn pick_config_for_window(
+    display: &Display,
+    raw_wnd: RawWindowHandle,
+    template: ConfigTemplate,
+) -> Result<Config, PickError> {
+    let visual = window_visual(raw_wnd);
+    let mut found_any = false;
+    let best = unsafe { display.find_configs(template)? }
+        .inspect(|_| found_any = true)
+        .filter(|config| match visual {
+            Some(visual) => native_visual_id(display, config) == Some(visual as i32),
+            None => true,
+        })
+        .max_by_key(|config| {
+            (
+                config.num_samples(),
+                std::cmp::Reverse(config.depth_size()),
+                std::cmp::Reverse(config.stencil_size()),
+            )
+        });
+    match (best, visual) {
+        (Some(config), _) => Ok(config),
+        (None, Some(visual)) if found_any => Err(PickError::NoVisualMatch(visual)),
+        (None, _) => Err(PickError::NoConfig),
+    }
+}
+
+/// Constraints for [`find_config_relaxed`], all of which may be dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConfigRequest {
+    pub color_depth: ColorDepth,
+    pub samples: u8,
+    pub srgb: bool,
+    pub depth_size: u8,
+    pub stencil_size: u8,
+}
+
+impl Default for ConfigRequest {
+    fn default() -> Self {
+        Self {
+            color_depth: ColorDepth::Srgb8,
+            samples: 0,
+            srgb: false,
+            depth_size: 0,
+            stencil_size: 0,
+        }
+    }
+}
+
+/// Constraints [`find_config_relaxed`] had to drop to find a config.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RelaxedConstraints {
+    pub samples: bool,
+    pub srgb: bool,
+    pub depth_stencil: bool,
+    pub color_depth: bool,
+}
+
+impl RelaxedConstraints {
+    /// Whether the config satisfies the request as given.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Finds a config for `request`, dropping constraints one at a time until one
+/// matches: multisampling first, then sRGB, then depth and stencil, then the
+/// color depth. With `raw_wnd` set only configs that can render to it are
+/// considered.
+pub fn find_config_relaxed(
+    display: &Display,
+    raw_wnd: Option<RawWindowHandle>,
+    request: ConfigRequest,
+) -> Result<(Config, RelaxedConstraints), PickError> {
+    let find = |request: &ConfigRequest| -> Result<Option<Config>, PickError> {
+        let mut template = ConfigTemplateBuilder::new()
+            .with_depth_size(request.depth_size)
+            .with_stencil_size(request.stencil_size);
+        if let Some(raw_wnd) = raw_wnd {
+            template = template
+                .compatible_with_native_window(raw_wnd)
+                .with_surface_type(ConfigSurfaceTypes::WINDOW);
+        }
+        if request.samples > 0 {
+            template = template.with_multisampling(request.samples);
+        }
+        let template = request.color_depth.apply(template).build();
+        Ok(unsafe { display.find_configs(template)? }
+            .find(|config| !request.srgb || config.srgb_capable()))
+    };
+
+    let mut request = request;
+    let mut relaxed = RelaxedConstraints::default();
+    loop {
+        if let Some(config) = find(&request)? {
+            return Ok((config, relaxed));
+        }
+        if request.samples > 0 {
+            request.samples = 0;
+            relaxed.samples = true;
+        } else if request.srgb {
+            request.srgb = false;
+            relaxed.srgb = true;
+        } else if request.depth_size > 0 || request.stencil_size > 0 {
+            request.depth_size = 0;
+            request.stencil_size = 0;
+            relaxed.depth_stencil = true;
+        } else if request.color_depth != ColorDepth::Srgb8 {
+            request.color_depth = ColorDepth::Srgb8;
+            relaxed.color_depth = true;
+        } else {
+            return Err(PickError::NoConfig);
+        }
+    }
+}
+
+/// How strongly a [`ConfigRequirements`] field constrains the config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttrRequirement<T> {
+    /// Configs that don't satisfy the value are rejected.
+    Required(T),
+    /// Configs that satisfy the value are ranked higher.
+    Preferred(T),
+    DontCare,
+}
+
+impl<T> Default for AttrRequirement<T> {
+    fn default() -> Self {
+        AttrRequirement::DontCare
+    }
+}
+
+impl<T: Copy> AttrRequirement<T> {
+    /// Whether `satisfied` rejects the config, and whether it adds to its rank.
+    fn check(self, satisfied: impl Fn(T) -> bool) -> (bool, bool) {
+        match self {
+            AttrRequirement::Required(value) => (!satisfied(value), false),
+            AttrRequirement::Preferred(value) => (false, satisfied(value)),
+            AttrRequirement::DontCare => (false, false),
+        }
+    }
+}
+
+/// Requirements for [`find_config_with`]. Sizes and samples are minimums,
+/// the color depth and sRGB have to match exactly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConfigRequirements {
+    pub color_depth: AttrRequirement<ColorDepth>,
+    pub samples: AttrRequirement<u8>,
+    pub srgb: AttrRequirement<bool>,
+    pub depth_size: AttrRequirement<u8>,
+    pub stencil_size: AttrRequirement<u8>,
+}
+
+/// Finds the config satisfying every required attribute of `req` that
+/// satisfies the most preferred ones, earlier fields of
+/// [`ConfigRequirements`] outweighing later ones. Ties go to the config with
+/// the fewest samples, depth and stencil bits. With `raw_wnd` set only
+/// configs that can render to it are considered.
+pub fn find_config_with(
+    display: &Display,
+    raw_wnd: Option<RawWindowHandle>,
+    req: &ConfigRequirements,
+) -> Result<Config, PickError> {
+    let mut template = ConfigTemplateBuilder::new();
+    if let Some(raw_wnd) = raw_wnd {
+        template = template
+            .compatible_with_native_window(raw_wnd)
+            .with_surface_type(ConfigSurfaceTypes::WINDOW);
+    }
+
+    unsafe { display.find_configs(template.build())? }
+        .filter_map(|config| {
+            let checks = [
+                req.color_depth
+                    .check(|depth| ColorDepth::of_config(&config) == depth),
+                req.samples.check(|samples| config.num_samples() >= samples),
+                req.srgb.check(|srgb| config.srgb_capable() == srgb),
+                req.depth_size.check(|size| config.depth_size() >= size),
+                req.stencil_size.check(|size| config.stencil_size() >= size),
+            ];
+            if checks.iter().any(|(rejected, _)| *rejected) {
+                return None;
+            }
+            let preferred = checks.map(|(_, preferred)| preferred);
+            Some((preferred, config))
+        })
+        .max_by_key(|(preferred, config)| {
+            (
+                *preferred,
+                std::cmp::Reverse(config.num_samples()),
+                std::cmp::Reverse(config.depth_size()),
+                std::cmp::Reverse(config.stencil_size()),
+            )
+        })
+        .map(|(_, config)| config)
+        .ok_or(PickError::NoConfig)
+}
+
+pub fn create_surface(
+    width: u32,
+    height: u32,
+    display: &Display,
+    config: &Config,
+    raw_wnd: RawWindowHandle,
+) -> Result<Surface<WindowSurface>, CreateError> {
+    let color_space = SurfaceColorSpace::Default;
+    create_surface_with_color_space(width, height, display, config, raw_wnd, color_space)
+        .map(|(surface, _)| surface)
+}
+
+/// Color space a window surface is tagged with, which color managed
+/// compositors, e.g. on Wayland, use to convert or tone map it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SurfaceColorSpace {
+    /// Nothing is requested, the platform default applies.
+    #[default]
+    Default,
+    /// `EGL_GL_COLORSPACE_SRGB`, needs an sRGB capable config.
+    Srgb,
+    /// `EGL_GL_COLORSPACE_LINEAR`.
+    Linear,
+    /// Linear extended range sRGB for HDR, from
+    /// `EGL_EXT_gl_colorspace_scrgb_linear`. glutin has no surface attribute
+    /// for it yet, so it is never granted.
+    ScrgbLinear,
+}
+
+/// Like [`create_surface`], tagging the surface with `color_space` where the
+/// backend supports it. Anything unsupported silently falls back to
+/// [`SurfaceColorSpace::Default`]. Returns the color space requested from
+/// the driver.
+pub fn create_surface_with_color_space(
+    width: u32,
+    height: u32,
+    display: &Display,
+    config: &Config,
+    raw_wnd: RawWindowHandle,
+    color_space: SurfaceColorSpace,
+) -> Result<(Surface<WindowSurface>, SurfaceColorSpace), CreateError> {
+    let (width, height) = match (NonZeroU32::new(width), NonZeroU32::new(height)) {
+        (Some(width), Some(height)) => (width, height),
+        _ => return Err(CreateError::ZeroSize),
+    };
+    // GLX takes sRGB from the config alone
+    let colorspace_ext = egl::has_extension(display, "EGL_KHR_gl_colorspace");
+    let granted = match color_space {
+        SurfaceColorSpace::Srgb if colorspace_ext && config.srgb_capable() => color_space,
+        SurfaceColorSpace::Linear if colorspace_ext => color_space,
+        _ => SurfaceColorSpace::Default,
+    };
+    let srgb = match granted {
+        SurfaceColorSpace::Srgb => Some(true),
+        SurfaceColorSpace::Linear => Some(false),
+        _ => None,
+    };
+    let attrs = SurfaceAttributesBuilder::<WindowSurface>::new()
+        .with_srgb(srgb)
+        .build(raw_wnd, width, height);
+    let surface =
+        unsafe { display.create_window_surface(config, &attrs) }.map_err(CreateError::Surface)?;
+    Ok((surface, granted))
+}
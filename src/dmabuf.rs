@@ -0,0 +1,294 @@
+use std::{
+    ffi::{c_void, CStr},
+    fmt,
+    os::unix::io::{AsRawFd, FromRawFd, OwnedFd},
+    ptr,
+};
+
+use glow::HasContext;
+use glutin::{
+    context::{AsRawContext, PossiblyCurrentContext, RawContext},
+    display::{AsRawDisplay, Display, RawDisplay},
+    prelude::PossiblyCurrentGlContext,
+};
+
+const EGL_GL_RENDERBUFFER_KHR: u32 = 0x30B9;
+const EGL_LINUX_DMA_BUF_EXT: u32 = 0x3270;
+const EGL_NONE: i32 = 0x3038;
+const EGL_WIDTH: i32 = 0x3057;
+const EGL_HEIGHT: i32 = 0x3056;
+const EGL_LINUX_DRM_FOURCC_EXT: i32 = 0x3271;
+const EGL_DMA_BUF_PLANE0_FD_EXT: i32 = 0x3272;
+const EGL_DMA_BUF_PLANE0_OFFSET_EXT: i32 = 0x3273;
+const EGL_DMA_BUF_PLANE0_PITCH_EXT: i32 = 0x3274;
+const EGL_DMA_BUF_PLANE0_MODIFIER_LO_EXT: i32 = 0x3443;
+const EGL_DMA_BUF_PLANE0_MODIFIER_HI_EXT: i32 = 0x3444;
+
+type EglCreateImageKhr = unsafe extern "C" fn(
+    dpy: *const c_void,
+    ctx: *const c_void,
+    target: u32,
+    buffer: *const c_void,
+    attribs: *const i32,
+) -> *const c_void;
+type EglDestroyImageKhr = unsafe extern "C" fn(dpy: *const c_void, image: *const c_void) -> u32;
+type EglExportDmabufImageQueryMesa = unsafe extern "C" fn(
+    dpy: *const c_void,
+    image: *const c_void,
+    fourcc: *mut i32,
+    num_planes: *mut i32,
+    modifiers: *mut u64,
+) -> u32;
+type EglExportDmabufImageMesa = unsafe extern "C" fn(
+    dpy: *const c_void,
+    image: *const c_void,
+    fds: *mut i32,
+    strides: *mut i32,
+    offsets: *mut i32,
+) -> u32;
+type GlEglImageTargetTexture2dOes = unsafe extern "C" fn(target: u32, image: *const c_void);
+
+#[derive(Debug)]
+pub struct DmabufFrame {
+    pub fd: OwnedFd,
+    pub fourcc: u32,
+    pub stride: u32,
+    pub offset: u32,
+    pub modifier: u64,
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    NotEgl,
+    MissingExtension(&'static str),
+    CreateImage,
+    Query,
+    MultiPlanar(i32),
+    Export,
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::NotEgl => write!(f, "dma-buf export requires an EGL display"),
+            ExportError::MissingExtension(ext) => write!(f, "{} is not supported", ext),
+            ExportError::CreateImage => write!(f, "eglCreateImageKHR failed"),
+            ExportError::Query => write!(f, "eglExportDMABUFImageQueryMESA failed"),
+            ExportError::MultiPlanar(n) => {
+                write!(f, "image has {} planes, only single plane is supported", n)
+            }
+            ExportError::Export => write!(f, "eglExportDMABUFImageMESA failed"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    NotEgl,
+    MissingExtension(&'static str),
+    CreateImage,
+    CreateTexture(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::NotEgl => write!(f, "dma-buf import requires an EGL display"),
+            ImportError::MissingExtension(ext) => write!(f, "{} is not supported", ext),
+            ImportError::CreateImage => write!(f, "eglCreateImageKHR failed"),
+            ImportError::CreateTexture(err) => write!(f, "failed to create texture: {}", err),
+        }
+    }
+}
+
+pub struct ImportedTexture {
+    pub texture: glow::NativeTexture,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ImportedTexture {
+    /// Attaches the texture as `COLOR_ATTACHMENT0` of `fb`, either to render
+    /// into it or to use it as the read framebuffer of a blit.
+    pub fn attach(&self, gl: &glow::Context, fb: glow::NativeFramebuffer) {
+        unsafe {
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+            gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(self.texture),
+                0,
+            );
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+        }
+    }
+
+    pub fn delete(self, gl: &glow::Context) {
+        unsafe { gl.delete_texture(self.texture) }
+    }
+}
+
+fn load<T>(c: &PossiblyCurrentContext, name: &[u8]) -> Option<T> {
+    let f = c.get_proc_address(CStr::from_bytes_with_nul(name).ok()?);
+    if f.is_null() {
+        return None;
+    }
+    Some(unsafe { std::mem::transmute_copy(&f) })
+}
+
+/// Exports `render_buf` as a dma-buf. `c` must be current and belong to the
+/// share group the renderbuffer was created in.
+pub fn export_dmabuf(
+    display: &Display,
+    c: &PossiblyCurrentContext,
+    render_buf: glow::NativeRenderbuffer,
+) -> Result<DmabufFrame, ExportError> {
+    let (dpy, ctx) = match (display.raw_display(), c.raw_context()) {
+        (RawDisplay::Egl(dpy), RawContext::Egl(ctx)) => (dpy, ctx),
+        _ => return Err(ExportError::NotEgl),
+    };
+
+    let create_image: EglCreateImageKhr = load(c, b"eglCreateImageKHR\0")
+        .ok_or(ExportError::MissingExtension("EGL_KHR_image_base"))?;
+    let destroy_image: EglDestroyImageKhr = load(c, b"eglDestroyImageKHR\0")
+        .ok_or(ExportError::MissingExtension("EGL_KHR_image_base"))?;
+    let export_query: EglExportDmabufImageQueryMesa = load(c, b"eglExportDMABUFImageQueryMESA\0")
+        .ok_or(ExportError::MissingExtension(
+        "EGL_MESA_image_dma_buf_export",
+    ))?;
+    let export: EglExportDmabufImageMesa = load(c, b"eglExportDMABUFImageMESA\0").ok_or(
+        ExportError::MissingExtension("EGL_MESA_image_dma_buf_export"),
+    )?;
+
+    let attribs = [EGL_NONE];
+    let image = unsafe {
+        create_image(
+            dpy,
+            ctx,
+            EGL_GL_RENDERBUFFER_KHR,
+            render_buf.0.get() as usize as *const c_void,
+            attribs.as_ptr(),
+        )
+    };
+    if image.is_null() {
+        return Err(ExportError::CreateImage);
+    }
+
+    let result = (|| {
+        let mut fourcc = 0;
+        let mut num_planes = 0;
+        let mut modifier = 0;
+        if unsafe { export_query(dpy, image, &mut fourcc, &mut num_planes, &mut modifier) } == 0 {
+            return Err(ExportError::Query);
+        }
+        if num_planes != 1 {
+            return Err(ExportError::MultiPlanar(num_planes));
+        }
+
+        let mut fd = -1;
+        let mut stride = 0;
+        let mut offset = 0;
+        if unsafe { export(dpy, image, &mut fd, &mut stride, &mut offset) } == 0 || fd < 0 {
+            return Err(ExportError::Export);
+        }
+
+        Ok(DmabufFrame {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            fourcc: fourcc as u32,
+            stride: stride as u32,
+            offset: offset as u32,
+            modifier,
+        })
+    })();
+
+    unsafe { destroy_image(dpy, image) };
+    result
+}
+
+/// Imports a single plane dma-buf described by `frame` as a `TEXTURE_2D`.
+/// The fd is not consumed, the texture keeps the underlying buffer alive.
+pub fn import_dmabuf(
+    display: &Display,
+    c: &PossiblyCurrentContext,
+    gl: &glow::Context,
+    frame: &DmabufFrame,
+    width: u32,
+    height: u32,
+) -> Result<ImportedTexture, ImportError> {
+    let dpy = match display.raw_display() {
+        RawDisplay::Egl(dpy) => dpy,
+        _ => return Err(ImportError::NotEgl),
+    };
+
+    let create_image: EglCreateImageKhr = load(c, b"eglCreateImageKHR\0")
+        .ok_or(ImportError::MissingExtension("EGL_KHR_image_base"))?;
+    let destroy_image: EglDestroyImageKhr = load(c, b"eglDestroyImageKHR\0")
+        .ok_or(ImportError::MissingExtension("EGL_KHR_image_base"))?;
+    let image_target: GlEglImageTargetTexture2dOes = load(c, b"glEGLImageTargetTexture2DOES\0")
+        .ok_or(ImportError::MissingExtension("GL_OES_EGL_image"))?;
+
+    let attribs = [
+        EGL_WIDTH,
+        width as i32,
+        EGL_HEIGHT,
+        height as i32,
+        EGL_LINUX_DRM_FOURCC_EXT,
+        frame.fourcc as i32,
+        EGL_DMA_BUF_PLANE0_FD_EXT,
+        frame.fd.as_raw_fd(),
+        EGL_DMA_BUF_PLANE0_OFFSET_EXT,
+        frame.offset as i32,
+        EGL_DMA_BUF_PLANE0_PITCH_EXT,
+        frame.stride as i32,
+        EGL_DMA_BUF_PLANE0_MODIFIER_LO_EXT,
+        frame.modifier as u32 as i32,
+        EGL_DMA_BUF_PLANE0_MODIFIER_HI_EXT,
+        (frame.modifier >> 32) as u32 as i32,
+        EGL_NONE,
+    ];
+    let image = unsafe {
+        create_image(
+            dpy,
+            ptr::null(),
+            EGL_LINUX_DMA_BUF_EXT,
+            ptr::null(),
+            attribs.as_ptr(),
+        )
+    };
+    if image.is_null() {
+        return Err(ImportError::CreateImage);
+    }
+
+    let texture = unsafe { gl.create_texture() };
+    let texture = match texture {
+        Ok(texture) => texture,
+        Err(err) => {
+            unsafe { destroy_image(dpy, image) };
+            return Err(ImportError::CreateTexture(err));
+        }
+    };
+    unsafe {
+        gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+        gl.tex_parameter_i32(
+            glow::TEXTURE_2D,
+            glow::TEXTURE_MIN_FILTER,
+            glow::LINEAR as i32,
+        );
+        gl.tex_parameter_i32(
+            glow::TEXTURE_2D,
+            glow::TEXTURE_MAG_FILTER,
+            glow::LINEAR as i32,
+        );
+        image_target(glow::TEXTURE_2D, image);
+        gl.bind_texture(glow::TEXTURE_2D, None);
+        // the texture is a sibling of the image and stays valid without it
+        destroy_image(dpy, image);
+    }
+
+    Ok(ImportedTexture {
+        texture,
+        width,
+        height,
+    })
+}
@@ -0,0 +1,126 @@
+use std::ffi::{c_void, CStr};
+
+use glutin::{
+    context::ContextApi,
+    display::{AsRawDisplay, Display, RawDisplay},
+    prelude::GlDisplay,
+    surface::{AsRawSurface, RawSurface, Surface, SurfaceTypeTrait},
+};
+
+const EGL_EXTENSIONS: i32 = 0x3055;
+const EGL_OPENGL_ES_API: u32 = 0x30A0;
+const EGL_OPENGL_API: u32 = 0x30A2;
+const EGL_BUFFER_AGE_EXT: i32 = 0x313D;
+const EGL_CONFIG_ID: i32 = 0x3028;
+
+type EglQueryString = unsafe extern "C" fn(dpy: *const c_void, name: i32) -> *const i8;
+type EglBindApi = unsafe extern "C" fn(api: u32) -> u32;
+type EglGetCurrentContext = unsafe extern "C" fn() -> *const c_void;
+type EglQuerySurface = unsafe extern "C" fn(
+    dpy: *const c_void,
+    surface: *const c_void,
+    attribute: i32,
+    value: *mut i32,
+) -> u32;
+
+pub(crate) fn raw_display(display: &Display) -> Option<*const c_void> {
+    match display.raw_display() {
+        RawDisplay::Egl(dpy) => Some(dpy),
+        _ => None,
+    }
+}
+
+pub(crate) fn raw_surface<T: SurfaceTypeTrait>(surface: &Surface<T>) -> Option<*const c_void> {
+    match surface.raw_surface() {
+        RawSurface::Egl(surface) => Some(surface),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+/// Loads an EGL entry point through the display, `name` must be nul terminated.
+pub(crate) fn load<T>(display: &Display, name: &[u8]) -> Option<T> {
+    let f = display.get_proc_address(CStr::from_bytes_with_nul(name).ok()?);
+    if f.is_null() {
+        return None;
+    }
+    Some(unsafe { std::mem::transmute_copy(&f) })
+}
+
+/// Whether the EGL display advertises `name`. Always `false` on other backends.
+pub fn has_extension(display: &Display, name: &str) -> bool {
+    let dpy = match raw_display(display) {
+        Some(dpy) => dpy,
+        None => return false,
+    };
+    let query_string: EglQueryString = match load(display, b"eglQueryString\0") {
+        Some(f) => f,
+        None => return false,
+    };
+
+    let extensions = unsafe { query_string(dpy, EGL_EXTENSIONS) };
+    if extensions.is_null() {
+        return false;
+    }
+    let extensions = unsafe { CStr::from_ptr(extensions) }.to_string_lossy();
+    extensions.split_ascii_whitespace().any(|ext| ext == name)
+}
+
+/// Binds the EGL client API matching `api` on the calling thread, contexts
+/// created afterwards on this thread use it. Returns `false` if the API is not
+/// supported, `true` without doing anything on other backends.
+pub fn bind_api(display: &Display, api: ContextApi) -> bool {
+    if raw_display(display).is_none() {
+        return true;
+    }
+    let bind_api: EglBindApi = match load(display, b"eglBindAPI\0") {
+        Some(f) => f,
+        None => return false,
+    };
+    let api = match api {
+        ContextApi::OpenGl(_) => EGL_OPENGL_API,
+        ContextApi::Gles(_) => EGL_OPENGL_ES_API,
+    };
+    unsafe { bind_api(api) != 0 }
+}
+
+/// The EGL context the driver has current on the calling thread, null if
+/// none. `None` on other backends.
+pub(crate) fn current_context(display: &Display) -> Option<*const c_void> {
+    raw_display(display)?;
+    let get_current_context: EglGetCurrentContext = load(display, b"eglGetCurrentContext\0")?;
+    Some(unsafe { get_current_context() })
+}
+
+/// `EGL_CONFIG_ID` of the config the raw EGL `surface` was created from.
+pub(crate) fn surface_config_id(display: &Display, surface: *const c_void) -> Option<i32> {
+    let dpy = raw_display(display)?;
+    let query_surface: EglQuerySurface = load(display, b"eglQuerySurface\0")?;
+    let mut id = 0;
+    if unsafe { query_surface(dpy, surface, EGL_CONFIG_ID, &mut id) } == 0 {
+        return None;
+    }
+    Some(id)
+}
+
+/// How many frames old the content of the back buffer of `surface` is, via
+/// `EGL_EXT_buffer_age`. `Some(0)` means the content is undefined and the
+/// whole surface has to be redrawn, `Some(n)` that it holds the frame
+/// presented `n` swaps ago. `surface` must be the current draw surface.
+/// `None` without the extension or on other backends.
+pub fn buffer_age<T: SurfaceTypeTrait>(display: &Display, surface: &Surface<T>) -> Option<u32> {
+    if !has_extension(display, "EGL_EXT_buffer_age")
+        && !has_extension(display, "EGL_KHR_partial_update")
+    {
+        return None;
+    }
+    let dpy = raw_display(display)?;
+    let surface = raw_surface(surface)?;
+    let query_surface: EglQuerySurface = load(display, b"eglQuerySurface\0")?;
+
+    let mut age = 0;
+    if unsafe { query_surface(dpy, surface, EGL_BUFFER_AGE_EXT, &mut age) } == 0 {
+        return None;
+    }
+    Some(age.max(0) as u32)
+}
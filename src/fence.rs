@@ -0,0 +1,124 @@
+use std::{
+    ffi::c_void,
+    fmt,
+    os::unix::io::{AsRawFd, IntoRawFd, OwnedFd},
+    time::Duration,
+};
+
+use glutin::display::Display;
+
+use crate::egl;
+
+const EGL_SYNC_NATIVE_FENCE_ANDROID: u32 = 0x3144;
+const EGL_SYNC_NATIVE_FENCE_FD_ANDROID: i32 = 0x3145;
+const EGL_SYNC_FLUSH_COMMANDS_BIT_KHR: i32 = 0x0001;
+const EGL_CONDITION_SATISFIED_KHR: i32 = 0x30F6;
+const EGL_NONE: i32 = 0x3038;
+
+type EglCreateSyncKhr =
+    unsafe extern "C" fn(dpy: *const c_void, ty: u32, attribs: *const i32) -> *const c_void;
+type EglDestroySyncKhr = unsafe extern "C" fn(dpy: *const c_void, sync: *const c_void) -> u32;
+type EglWaitSyncKhr =
+    unsafe extern "C" fn(dpy: *const c_void, sync: *const c_void, flags: i32) -> i32;
+type EglClientWaitSyncKhr =
+    unsafe extern "C" fn(dpy: *const c_void, sync: *const c_void, flags: i32, timeout: u64) -> i32;
+
+#[derive(Debug)]
+pub enum FenceError {
+    NotEgl,
+    MissingExtension(&'static str),
+    Import,
+    Wait,
+}
+
+impl fmt::Display for FenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenceError::NotEgl => write!(f, "fence import requires an EGL display"),
+            FenceError::MissingExtension(ext) => write!(f, "{} is not supported", ext),
+            FenceError::Import => write!(f, "eglCreateSyncKHR failed"),
+            FenceError::Wait => write!(f, "eglWaitSyncKHR failed"),
+        }
+    }
+}
+
+/// A sync object created from a native fence fd, e.g. one exported from a
+/// Vulkan semaphore with `VK_EXTERNAL_SEMAPHORE_HANDLE_TYPE_SYNC_FD_BIT`.
+pub struct GlFence {
+    dpy: *const c_void,
+    sync: *const c_void,
+    destroy: EglDestroySyncKhr,
+    wait: EglWaitSyncKhr,
+    client_wait: EglClientWaitSyncKhr,
+}
+
+impl GlFence {
+    /// Makes the GPU wait for the fence before executing commands issued
+    /// afterwards on the current context, without blocking the CPU.
+    pub fn wait(&self) -> Result<(), FenceError> {
+        if unsafe { (self.wait)(self.dpy, self.sync, 0) } == 0 {
+            return Err(FenceError::Wait);
+        }
+        Ok(())
+    }
+
+    /// Blocks the calling thread until the fence signals, returns `false` if
+    /// `timeout` elapsed first.
+    pub fn client_wait(&self, timeout: Duration) -> bool {
+        let timeout = timeout.as_nanos().min(u64::MAX as u128) as u64;
+        let res = unsafe {
+            (self.client_wait)(
+                self.dpy,
+                self.sync,
+                EGL_SYNC_FLUSH_COMMANDS_BIT_KHR,
+                timeout,
+            )
+        };
+        res == EGL_CONDITION_SATISFIED_KHR
+    }
+}
+
+impl Drop for GlFence {
+    fn drop(&mut self) {
+        unsafe { (self.destroy)(self.dpy, self.sync) };
+    }
+}
+
+/// Imports a native fence fd through `EGL_ANDROID_native_fence_sync`. EGL
+/// takes ownership of the fd on success, it is closed on failure.
+pub fn import_fence_fd(display: &Display, fd: OwnedFd) -> Result<GlFence, FenceError> {
+    let dpy = egl::raw_display(display).ok_or(FenceError::NotEgl)?;
+    if !egl::has_extension(display, "EGL_ANDROID_native_fence_sync") {
+        return Err(FenceError::MissingExtension(
+            "EGL_ANDROID_native_fence_sync",
+        ));
+    }
+    if !egl::has_extension(display, "EGL_KHR_wait_sync") {
+        return Err(FenceError::MissingExtension("EGL_KHR_wait_sync"));
+    }
+
+    let create: EglCreateSyncKhr = egl::load(display, b"eglCreateSyncKHR\0")
+        .ok_or(FenceError::MissingExtension("EGL_KHR_fence_sync"))?;
+    let destroy: EglDestroySyncKhr = egl::load(display, b"eglDestroySyncKHR\0")
+        .ok_or(FenceError::MissingExtension("EGL_KHR_fence_sync"))?;
+    let client_wait: EglClientWaitSyncKhr = egl::load(display, b"eglClientWaitSyncKHR\0")
+        .ok_or(FenceError::MissingExtension("EGL_KHR_fence_sync"))?;
+    let wait: EglWaitSyncKhr = egl::load(display, b"eglWaitSyncKHR\0")
+        .ok_or(FenceError::MissingExtension("EGL_KHR_wait_sync"))?;
+
+    let attribs = [EGL_SYNC_NATIVE_FENCE_FD_ANDROID, fd.as_raw_fd(), EGL_NONE];
+    let sync = unsafe { create(dpy, EGL_SYNC_NATIVE_FENCE_ANDROID, attribs.as_ptr()) };
+    if sync.is_null() {
+        return Err(FenceError::Import);
+    }
+    // owned by the sync object from here on
+    let _ = fd.into_raw_fd();
+
+    Ok(GlFence {
+        dpy,
+        sync,
+        destroy,
+        wait,
+        client_wait,
+    })
+}
@@ -0,0 +1,299 @@
+use std::fmt;
+
+use glow::HasContext;
+
+use crate::{
+    caps::GlCaps,
+    context::{ContextWrapper, GlObject, ObjectError},
+    rect::Rect,
+    target::pixel_transfer,
+};
+
+/// Handle to a texture backed target owned by a [`FrameGraph`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TargetId(usize);
+
+/// Where a pass renders to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PassOutput {
+    Target(TargetId),
+    /// A framebuffer of the headless context, e.g.
+    /// [`SharedTarget::headless_fb`](crate::SharedTarget::headless_fb) for the
+    /// pass whose result is blitted to the window.
+    Framebuffer(glow::NativeFramebuffer, (u32, u32)),
+}
+
+/// Why a pass could not be added to or run by a [`FrameGraph`].
+#[derive(Debug)]
+pub enum GraphError {
+    /// The target was not created by this graph.
+    UnknownTarget(TargetId),
+    /// The named pass reads the target before the later pass that renders
+    /// into it ran.
+    ForwardReference {
+        pass: String,
+        target: TargetId,
+    },
+    Context(glutin::error::Error),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::UnknownTarget(id) => write!(f, "{:?} is not a target of this graph", id),
+            GraphError::ForwardReference { pass, target } => write!(
+                f,
+                "pass `{}` reads {:?} before a later pass renders into it",
+                pass, target
+            ),
+            GraphError::Context(err) => write!(f, "context error: {}", err),
+        }
+    }
+}
+
+impl From<glutin::error::Error> for GraphError {
+    fn from(err: glutin::error::Error) -> Self {
+        GraphError::Context(err)
+    }
+}
+
+struct Target {
+    framebuffer: glow::NativeFramebuffer,
+    texture: glow::NativeTexture,
+    size: (u32, u32),
+}
+
+struct Pass {
+    name: String,
+    inputs: Vec<TargetId>,
+    output: PassOutput,
+    draw: Box<dyn FnMut(&glow::Context)>,
+}
+
+/// A list of offscreen passes run in the headless context in the order they
+/// were added. Before a pass draws, its output is bound with a viewport
+/// covering it and its inputs are bound to texture units `0..inputs.len()`.
+#[derive(Default)]
+pub struct FrameGraph {
+    targets: Vec<Target>,
+    passes: Vec<Pass>,
+}
+
+impl FrameGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a texture and a framebuffer rendering into it in the headless
+    /// context. They are deleted on [`ContextWrapper::shutdown`]. The texture
+    /// has immutable storage where [`GlCaps::has_texture_storage`].
+    pub fn create_target(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glh: &glow::Context,
+        format: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<TargetId, ObjectError> {
+        let c = ctx.try_ct_head()?;
+        let target = unsafe {
+            glh.create_texture().and_then(|texture| {
+                ctx.track_head(GlObject::Texture(texture));
+                glh.bind_texture(glow::TEXTURE_2D, Some(texture));
+                if GlCaps::query(glh).has_texture_storage() {
+                    glh.tex_storage_2d(glow::TEXTURE_2D, 1, format, width as _, height as _);
+                } else {
+                    let (pixel_format, ty) = pixel_transfer(format);
+                    glh.tex_image_2d(
+                        glow::TEXTURE_2D,
+                        0,
+                        format as i32,
+                        width as _,
+                        height as _,
+                        0,
+                        pixel_format,
+                        ty,
+                        None,
+                    );
+                }
+                for (param, value) in [
+                    (glow::TEXTURE_MIN_FILTER, glow::LINEAR),
+                    (glow::TEXTURE_MAG_FILTER, glow::LINEAR),
+                    (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+                    (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+                ] {
+                    glh.tex_parameter_i32(glow::TEXTURE_2D, param, value as i32);
+                }
+                glh.bind_texture(glow::TEXTURE_2D, None);
+
+                let framebuffer = glh.create_framebuffer()?;
+                ctx.track_head(GlObject::Framebuffer(framebuffer));
+                glh.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+                glh.framebuffer_texture_2d(
+                    glow::FRAMEBUFFER,
+                    glow::COLOR_ATTACHMENT0,
+                    glow::TEXTURE_2D,
+                    Some(texture),
+                    0,
+                );
+                glh.bind_framebuffer(glow::FRAMEBUFFER, None);
+                Ok(Target {
+                    framebuffer,
+                    texture,
+                    size: (width, height),
+                })
+            })
+        };
+        ctx.put_head(c)?;
+
+        self.targets.push(target?);
+        Ok(TargetId(self.targets.len() - 1))
+    }
+
+    /// `None` for a target of another graph.
+    pub fn texture(&self, id: TargetId) -> Option<glow::NativeTexture> {
+        self.targets.get(id.0).map(|target| target.texture)
+    }
+
+    /// Appends a pass. Fails if an input or the output is not a target of
+    /// this graph.
+    pub fn add_pass(
+        &mut self,
+        name: impl Into<String>,
+        inputs: &[TargetId],
+        output: PassOutput,
+        draw: impl FnMut(&glow::Context) + 'static,
+    ) -> Result<(), GraphError> {
+        let output_target = match output {
+            PassOutput::Target(id) => Some(id),
+            PassOutput::Framebuffer(..) => None,
+        };
+        if let Some(&id) = inputs
+            .iter()
+            .chain(&output_target)
+            .find(|id| id.0 >= self.targets.len())
+        {
+            return Err(GraphError::UnknownTarget(id));
+        }
+        self.passes.push(Pass {
+            name: name.into(),
+            inputs: inputs.to_vec(),
+            output,
+            draw: Box::new(draw),
+        });
+        Ok(())
+    }
+
+    /// Fails if a pass reads a target that only a later pass renders into.
+    /// Targets no pass renders into hold whatever was put there outside of
+    /// the graph and may be read by any pass.
+    fn check_order(&self) -> Result<(), GraphError> {
+        let writes = |pass: &Pass, id: TargetId| pass.output == PassOutput::Target(id);
+        for (i, pass) in self.passes.iter().enumerate() {
+            for &id in &pass.inputs {
+                let written_before = self.passes[..i].iter().any(|p| writes(p, id));
+                let written_later = self.passes[i + 1..].iter().any(|p| writes(p, id));
+                if !written_before && written_later {
+                    return Err(GraphError::ForwardReference {
+                        pass: pass.name.clone(),
+                        target: id,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Names of the passes in execution order.
+    pub fn pass_names(&self) -> impl Iterator<Item = &str> {
+        self.passes.iter().map(|pass| pass.name.as_str())
+    }
+
+    /// Makes the headless context current and runs every pass once, then
+    /// flushes so the window context sees the results. Fails before running
+    /// anything if a pass reads a target a later pass renders into.
+    pub fn execute(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glh: &glow::Context,
+    ) -> Result<(), GraphError> {
+        self.check_order()?;
+        let c = ctx.try_ct_head()?;
+        for pass in &mut self.passes {
+            let (framebuffer, (width, height)) = match pass.output {
+                PassOutput::Target(id) => {
+                    let target = &self.targets[id.0];
+                    (target.framebuffer, target.size)
+                }
+                PassOutput::Framebuffer(fb, size) => (fb, size),
+            };
+            unsafe {
+                glh.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+                for (unit, id) in pass.inputs.iter().enumerate() {
+                    glh.active_texture(glow::TEXTURE0 + unit as u32);
+                    glh.bind_texture(glow::TEXTURE_2D, Some(self.targets[id.0].texture));
+                }
+            }
+            Rect::from_size(width, height).viewport(glh);
+
+            (pass.draw)(glh);
+
+            unsafe {
+                for unit in (0..pass.inputs.len()).rev() {
+                    glh.active_texture(glow::TEXTURE0 + unit as u32);
+                    glh.bind_texture(glow::TEXTURE_2D, None);
+                }
+            }
+        }
+        unsafe {
+            glh.bind_framebuffer(glow::FRAMEBUFFER, None);
+            glh.flush();
+        }
+        ctx.put_head(c)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pass(name: &str, inputs: &[TargetId], output: TargetId) -> Pass {
+        Pass {
+            name: name.into(),
+            inputs: inputs.to_vec(),
+            output: PassOutput::Target(output),
+            draw: Box::new(|_: &glow::Context| {}),
+        }
+    }
+
+    #[test]
+    fn unknown_target() {
+        let mut graph = FrameGraph::new();
+        let result = graph.add_pass(
+            "blur",
+            &[TargetId(0)],
+            PassOutput::Target(TargetId(1)),
+            |_| {},
+        );
+        assert!(matches!(
+            result,
+            Err(GraphError::UnknownTarget(TargetId(0)))
+        ));
+        assert_eq!(graph.pass_names().count(), 0);
+    }
+
+    #[test]
+    fn forward_reference() {
+        let mut graph = FrameGraph::new();
+        graph.passes.push(pass("blur", &[TargetId(0)], TargetId(1)));
+        graph.passes.push(pass("scene", &[], TargetId(0)));
+        assert!(matches!(
+            graph.check_order(),
+            Err(GraphError::ForwardReference { pass, target: TargetId(0) }) if pass == "blur"
+        ));
+
+        graph.passes.reverse();
+        assert!(graph.check_order().is_ok());
+    }
+}
@@ -0,0 +1,68 @@
+#[cfg(all(feature = "winit-027", feature = "winit-028"))]
+compile_error!("the winit-027 and winit-028 features are mutually exclusive");
+#[cfg(not(any(feature = "winit-027", feature = "winit-028")))]
+compile_error!("one of the winit-027 and winit-028 features is required");
+
+/// The winit version the crate was built against, for downstreams to create
+/// the event loop and windows with.
+#[cfg(feature = "winit-027")]
+pub extern crate winit;
+#[cfg(feature = "winit-028")]
+pub extern crate winit_028 as winit;
+
+mod app;
+mod caps;
+pub mod capture;
+pub mod compositor;
+mod context;
+pub mod diagnostics;
+mod display;
+#[cfg(target_os = "linux")]
+pub mod dmabuf;
+pub mod egl;
+#[cfg(target_os = "linux")]
+pub mod fence;
+pub mod graph;
+pub mod monitor;
+mod offscreen;
+pub mod present;
+mod rect;
+mod surface;
+pub mod sync;
+mod target;
+pub mod timing;
+pub mod uniform;
+#[cfg(feature = "video")]
+pub mod video;
+pub mod wall;
+#[cfg(target_os = "linux")]
+pub mod x11;
+
+pub use app::{
+    AppBuilder, AppError, Frame, FrameError, RenderMode, RenderModeToggle, ResizeTiming,
+};
+#[cfg(feature = "pump")]
+pub use app::{PumpStatus, PumpedApp};
+pub use caps::GlCaps;
+pub use context::{
+    create_shared_contexts, drain_gl_errors, load_gl, load_gl_tracked, ContextId, ContextOptions,
+    ContextWrapper, CreateError, GlObject, GlVersionRequest, ObjectError, PartialUpdateError,
+    SharingCaps, SharingDiagnostic, WorkerContext,
+};
+pub use display::{
+    config_by_id, config_id, create_surface, create_surface_with_color_space, find_config_relaxed,
+    find_config_with, find_headless_config, native_visual_id, pick_config_for_window, sample_steps,
+    select_display_config, select_display_config_from, AttrRequirement, Backend, ColorDepth,
+    ConfigRequest, ConfigRequirements, DisplayConfig, EffectiveConfig, PickError,
+    RelaxedConstraints, SurfaceColorSpace,
+};
+pub use offscreen::{OffscreenRenderer, RenderError};
+pub use rect::{set_viewports, Rect};
+pub use surface::{
+    create_headless_surface, present, AnySurface, Buffering, HeadlessKind, HeadlessPresent,
+    HeadlessSurface, PresentCaps, SurfaceInfo, SurfaceKind,
+};
+pub use target::{
+    check_resolve_format, LocalTarget, MsaaConfig, RenderTargetConfig, RenderTargetSizing,
+    SharedTarget,
+};
@@ -0,0 +1,105 @@
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event_loop::EventLoopWindowTarget,
+    monitor::{MonitorHandle, VideoMode},
+    window::Fullscreen,
+};
+
+/// Picks the monitor a fullscreen window is created on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MonitorSelector {
+    Primary,
+    /// Index into `available_monitors`.
+    Index(usize),
+    /// Monitor name as reported by winit, e.g. `DP-1`.
+    Name(String),
+}
+
+impl MonitorSelector {
+    /// Parses an index if `s` is numeric, a name otherwise.
+    pub fn parse(s: &str) -> Self {
+        match s.parse() {
+            Ok(index) => MonitorSelector::Index(index),
+            Err(_) => MonitorSelector::Name(s.to_owned()),
+        }
+    }
+
+    pub fn find<T>(&self, target: &EventLoopWindowTarget<T>) -> Option<MonitorHandle> {
+        match self {
+            MonitorSelector::Primary => target
+                .primary_monitor()
+                .or_else(|| target.available_monitors().next()),
+            MonitorSelector::Index(index) => target.available_monitors().nth(*index),
+            MonitorSelector::Name(name) => target
+                .available_monitors()
+                .find(|m| m.name().as_deref() == Some(name.as_str())),
+        }
+    }
+}
+
+/// Lists the available monitors with their current size, in the order used by
+/// [`MonitorSelector::Index`].
+pub fn list_monitors<T>(target: &EventLoopWindowTarget<T>) -> Vec<(String, PhysicalSize<u32>)> {
+    target
+        .available_monitors()
+        .map(|m| (m.name().unwrap_or_default(), m.size()))
+        .collect()
+}
+
+/// The largest video mode of `monitor`, preferring higher refresh rates and
+/// bit depths among modes of the same size.
+pub fn best_video_mode(monitor: &MonitorHandle) -> Option<VideoMode> {
+    monitor.video_modes().max_by_key(|mode| {
+        let size = mode.size();
+        (
+            size.width as u64 * size.height as u64,
+            mode.refresh_rate_millihertz(),
+            mode.bit_depth(),
+        )
+    })
+}
+
+/// Builds an exclusive fullscreen request for the selected monitor, or
+/// `None` for a regular window or if the monitor does not exist. Falls back
+/// to borderless fullscreen if the monitor reports no video modes.
+pub fn fullscreen<T>(
+    target: &EventLoopWindowTarget<T>,
+    selector: Option<MonitorSelector>,
+) -> Option<Fullscreen> {
+    let monitor = selector?.find(target)?;
+    Some(match best_video_mode(&monitor) {
+        Some(mode) => Fullscreen::Exclusive(mode),
+        None => Fullscreen::Borderless(Some(monitor)),
+    })
+}
+
+/// Where a regular window is placed: `position` relative to the origin of
+/// the selected monitor, or in desktop coordinates without a monitor. `None`
+/// leaves the placement to the window manager, as does a monitor that does
+/// not exist.
+pub fn window_position<T>(
+    target: &EventLoopWindowTarget<T>,
+    monitor: Option<MonitorSelector>,
+    position: Option<PhysicalPosition<i32>>,
+) -> Option<PhysicalPosition<i32>> {
+    let offset = position.unwrap_or(PhysicalPosition::new(0, 0));
+    match monitor {
+        Some(selector) => {
+            let origin = selector.find(target)?.position();
+            Some(PhysicalPosition::new(
+                origin.x + offset.x,
+                origin.y + offset.y,
+            ))
+        }
+        None => position,
+    }
+}
+
+/// The size a window will have once `fullscreen` is applied, which the window
+/// itself may not report until the mode switch has happened.
+pub fn fullscreen_size(fullscreen: &Fullscreen) -> Option<PhysicalSize<u32>> {
+    match fullscreen {
+        Fullscreen::Exclusive(mode) => Some(mode.size()),
+        Fullscreen::Borderless(monitor) => monitor.as_ref().map(|m| m.size()),
+    }
+}
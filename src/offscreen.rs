@@ -0,0 +1,205 @@
+use std::fmt;
+
+use glow::HasContext;
+use glutin::{
+    context::{NotCurrentContext, PossiblyCurrentContext},
+    error::ErrorKind,
+    prelude::PossiblyCurrentGlContext,
+};
+
+use crate::{context::load_gl, rect::Rect, surface::AnySurface};
+
+#[derive(Debug)]
+pub enum RenderError {
+    Context(glutin::error::Error),
+    Gl(String),
+    #[cfg(feature = "video")]
+    Record(std::io::Error),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::Context(err) => write!(f, "context error: {}", err),
+            RenderError::Gl(err) => write!(f, "gl error: {}", err),
+            #[cfg(feature = "video")]
+            RenderError::Record(err) => write!(f, "recording failed: {}", err),
+        }
+    }
+}
+
+impl From<glutin::error::Error> for RenderError {
+    fn from(err: glutin::error::Error) -> Self {
+        RenderError::Context(err)
+    }
+}
+
+/// Renders into an offscreen renderbuffer without any event loop involvement.
+/// Every method leaves the context not current when it returns.
+pub struct OffscreenRenderer {
+    gl: glow::Context,
+    framebuffer: glow::NativeFramebuffer,
+    renderbuffer: glow::NativeRenderbuffer,
+    format: u32,
+    width: u32,
+    height: u32,
+    render: Box<dyn FnMut(&glow::Context)>,
+    #[cfg(feature = "video")]
+    recorder: Option<crate::video::Recorder>,
+    // dropped before the context
+    surface: AnySurface,
+    context: Option<NotCurrentContext>,
+}
+
+impl OffscreenRenderer {
+    pub fn new(
+        context: NotCurrentContext,
+        surface: impl Into<AnySurface>,
+        format: u32,
+        width: u32,
+        height: u32,
+        render: impl FnMut(&glow::Context) + 'static,
+    ) -> Result<Self, RenderError> {
+        let surface = surface.into();
+        let c = surface.make_current(context)?;
+        let gl = load_gl(&c);
+
+        let objects = unsafe {
+            gl.create_renderbuffer().and_then(|rb| {
+                gl.bind_renderbuffer(glow::RENDERBUFFER, Some(rb));
+                gl.renderbuffer_storage(glow::RENDERBUFFER, format, width as _, height as _);
+                let fb = gl.create_framebuffer()?;
+                gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+                gl.framebuffer_renderbuffer(
+                    glow::FRAMEBUFFER,
+                    glow::COLOR_ATTACHMENT0,
+                    glow::RENDERBUFFER,
+                    Some(rb),
+                );
+                gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+                Ok((fb, rb))
+            })
+        };
+        let context = Some(c.make_not_current()?);
+        let (framebuffer, renderbuffer) = objects.map_err(RenderError::Gl)?;
+
+        Ok(Self {
+            gl,
+            framebuffer,
+            renderbuffer,
+            format,
+            width,
+            height,
+            render: Box::new(render),
+            #[cfg(feature = "video")]
+            recorder: None,
+            surface,
+            context,
+        })
+    }
+
+    fn make_current(&mut self) -> Result<PossiblyCurrentContext, RenderError> {
+        let context = self
+            .context
+            .take()
+            .ok_or(glutin::error::Error::from(ErrorKind::BadContextState));
+        Ok(self.surface.make_current(context?)?)
+    }
+
+    fn make_not_current(&mut self, c: PossiblyCurrentContext) -> Result<(), RenderError> {
+        self.context = Some(c.make_not_current()?);
+        Ok(())
+    }
+
+    /// Runs the render callback once with the offscreen framebuffer bound and
+    /// the viewport covering it.
+    pub fn render_frame(&mut self) -> Result<(), RenderError> {
+        let c = self.make_current()?;
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+        }
+        Rect::from_size(self.width, self.height).viewport(&self.gl);
+        (self.render)(&self.gl);
+        #[cfg(feature = "video")]
+        let recorded = match &mut self.recorder {
+            Some(recorder) => {
+                let pixels = crate::capture::read_rgba(&self.gl, self.width, self.height);
+                recorder.write_frame(&pixels, self.width, self.height)
+            }
+            None => Ok(()),
+        };
+        unsafe {
+            self.gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            self.gl.flush();
+        }
+        self.make_not_current(c)?;
+        #[cfg(feature = "video")]
+        recorded.map_err(RenderError::Record)?;
+        Ok(())
+    }
+
+    /// Appends every frame rendered from now on to a Y4M file at `path`, or
+    /// stdout for `-`, played back at `fps`. The size has to stay the same
+    /// while recording. Replaces a running recording without finishing it.
+    #[cfg(feature = "video")]
+    pub fn start_recording(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+        fps: u32,
+    ) -> Result<(), RenderError> {
+        let recorder = crate::video::Recorder::create(path, fps).map_err(RenderError::Record)?;
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    /// Finishes the recording, returning the number of frames written or
+    /// `None` if nothing was being recorded.
+    #[cfg(feature = "video")]
+    pub fn stop_recording(&mut self) -> Result<Option<u64>, RenderError> {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish().map(Some).map_err(RenderError::Record),
+            None => Ok(None),
+        }
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), RenderError> {
+        let c = self.make_current()?;
+        unsafe {
+            self.gl
+                .bind_renderbuffer(glow::RENDERBUFFER, Some(self.renderbuffer));
+            self.gl
+                .renderbuffer_storage(glow::RENDERBUFFER, self.format, width as _, height as _);
+        }
+        self.width = width;
+        self.height = height;
+        self.make_not_current(c)
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn renderbuffer(&self) -> glow::NativeRenderbuffer {
+        self.renderbuffer
+    }
+
+    pub fn gl(&self) -> &glow::Context {
+        &self.gl
+    }
+}
+
+impl Drop for OffscreenRenderer {
+    fn drop(&mut self) {
+        if self.context.is_none() {
+            return;
+        }
+        if let Ok(c) = self.make_current() {
+            unsafe {
+                self.gl.delete_framebuffer(self.framebuffer);
+                self.gl.delete_renderbuffer(self.renderbuffer);
+            }
+            let _ = self.make_not_current(c);
+        }
+    }
+}
@@ -0,0 +1,504 @@
+use std::fmt;
+
+use glow::HasContext;
+
+use crate::{caps::GlCaps, rect::Rect};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AspectMode {
+    /// Scale the source to the whole window, ignoring its aspect ratio.
+    Stretch,
+    /// Scale the source to fit inside the window, leaving bars on two sides.
+    Fit,
+    /// Scale the source to cover the window, cropping it on two sides.
+    Fill,
+}
+
+/// Fits `src` into `dst` according to `mode`, returning the source and
+/// destination rectangles of the blit.
+pub fn blit_rects(mode: AspectMode, src: Rect, dst: Rect) -> (Rect, Rect) {
+    if src.is_empty() || dst.is_empty() {
+        return (src, dst);
+    }
+
+    let (sw, sh) = (src.width as f64, src.height as f64);
+    let (dw, dh) = (dst.width as f64, dst.height as f64);
+    match mode {
+        AspectMode::Stretch => (src, dst),
+        AspectMode::Fit => {
+            let scale = (dw / sw).min(dh / sh);
+            let (w, h) = ((sw * scale).round(), (sh * scale).round());
+            let (x, y) = (((dw - w) / 2.0).floor(), ((dh - h) / 2.0).floor());
+            (
+                src,
+                Rect::new(dst.x + x as i32, dst.y + y as i32, w as u32, h as u32),
+            )
+        }
+        AspectMode::Fill => {
+            let scale = (dw / sw).max(dh / sh);
+            let (w, h) = ((dw / scale).round(), (dh / scale).round());
+            let (x, y) = (((sw - w) / 2.0).floor(), ((sh - h) / 2.0).floor());
+            (
+                Rect::new(src.x + x as i32, src.y + y as i32, w as u32, h as u32),
+                dst,
+            )
+        }
+    }
+}
+
+/// Blits `src` of the bound read framebuffer to `dst` of the bound draw
+/// framebuffer, filtering linearly if the sizes differ.
+pub fn blit(gl: &glow::Context, src: Rect, dst: Rect) {
+    let [sx0, sy0, sx1, sy1] = src.corners();
+    let [dx0, dy0, dx1, dy1] = dst.corners();
+    let filter = if src.size() == dst.size() {
+        glow::NEAREST
+    } else {
+        glow::LINEAR
+    };
+    unsafe {
+        gl.blit_framebuffer(
+            sx0,
+            sy0,
+            sx1,
+            sy1,
+            dx0,
+            dy0,
+            dx1,
+            dy1,
+            glow::COLOR_BUFFER_BIT,
+            filter,
+        );
+    }
+}
+
+/// Why [`blit_to_window`] or [`QuadPresenter::draw`] did not draw.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlitError {
+    /// The source rect has no area, e.g. a zero internal resolution.
+    EmptySource(Rect),
+    /// The destination rect has no area, or the fitted one rounded to none.
+    EmptyDestination(Rect),
+    /// The read framebuffer is incomplete, with the status it reported.
+    Incomplete(u32),
+}
+
+impl fmt::Display for BlitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlitError::EmptySource(rect) => write!(f, "blit source {:?} is empty", rect),
+            BlitError::EmptyDestination(rect) => {
+                write!(f, "blit destination {:?} is empty", rect)
+            }
+            BlitError::Incomplete(status) => write!(
+                f,
+                "blit source framebuffer is incomplete (status 0x{:04X})",
+                status
+            ),
+        }
+    }
+}
+
+/// Like [`blit_rects`], but fails if either rect of the blit has no area,
+/// in which case `glBlitFramebuffer` would silently draw nothing. Needs no
+/// GL context.
+pub fn checked_blit_rects(
+    mode: AspectMode,
+    src: Rect,
+    dst: Rect,
+) -> Result<(Rect, Rect), BlitError> {
+    if src.is_empty() {
+        return Err(BlitError::EmptySource(src));
+    }
+    if dst.is_empty() {
+        return Err(BlitError::EmptyDestination(dst));
+    }
+    match blit_rects(mode, src, dst) {
+        (src, _) if src.is_empty() => Err(BlitError::EmptySource(src)),
+        (_, fitted) if fitted.is_empty() => Err(BlitError::EmptyDestination(fitted)),
+        rects => Ok(rects),
+    }
+}
+
+/// Blits `src` of `read_fb` to `dst` of the default framebuffer of the
+/// current context. Any area of `dst` not covered by the blit is cleared to
+/// `bar_color`. Follow with [`AlphaPass::apply`] on `dst` when presenting to a
+/// transparent window.
+///
+/// Nothing is drawn if the rects fail [`checked_blit_rects`] or `read_fb` is
+/// incomplete.
+pub fn blit_to_window(
+    gl: &glow::Context,
+    read_fb: glow::NativeFramebuffer,
+    src: Rect,
+    dst: Rect,
+    mode: AspectMode,
+    bar_color: [f32; 4],
+) -> Result<(), BlitError> {
+    let (src, fitted) = checked_blit_rects(mode, src, dst)?;
+    unsafe {
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(read_fb));
+        let status = gl.check_framebuffer_status(glow::READ_FRAMEBUFFER);
+        if status != glow::FRAMEBUFFER_COMPLETE {
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+            return Err(BlitError::Incomplete(status));
+        }
+        gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, None);
+        if fitted != dst {
+            let [r, g, b, a] = bar_color;
+            gl.enable(glow::SCISSOR_TEST);
+            dst.scissor(gl);
+            gl.clear_color(r, g, b, a);
+            gl.clear(glow::COLOR_BUFFER_BIT);
+            gl.disable(glow::SCISSOR_TEST);
+        }
+    }
+    blit(gl, src, fitted);
+    Ok(())
+}
+
+/// How the alpha channel of the presented image is treated. The shared buffer
+/// is assumed to hold straight alpha.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AlphaMode {
+    /// Copy the buffer as is, for opaque windows.
+    #[default]
+    Ignore,
+    /// Copy the buffer as is, for compositors expecting straight alpha.
+    Straight,
+    /// Multiply the color by alpha after the blit, for compositors expecting
+    /// premultiplied alpha.
+    Premultiplied,
+}
+
+const PREMULTIPLY_VS: &str = "
+const vec2 verts[3] = vec2[3](vec2(-1.0, -1.0), vec2(3.0, -1.0), vec2(-1.0, 3.0));
+void main() {
+    gl_Position = vec4(verts[gl_VertexID], 0.0, 1.0);
+}
+";
+const PREMULTIPLY_FS: &str = "
+precision mediump float;
+out vec4 color;
+void main() {
+    color = vec4(0.0);
+}
+";
+
+/// Applies an [`AlphaMode`] to the default framebuffer after a blit. Only
+/// [`AlphaMode::Premultiplied`] allocates GL objects, in the context `new`
+/// was called in.
+pub struct AlphaPass {
+    mode: AlphaMode,
+    objects: Option<(glow::NativeProgram, Option<glow::NativeVertexArray>)>,
+}
+
+impl AlphaPass {
+    pub fn new(gl: &glow::Context, caps: &GlCaps, mode: AlphaMode) -> Result<Self, String> {
+        let objects = match mode {
+            AlphaMode::Premultiplied => {
+                Some(unsafe { program(gl, caps, PREMULTIPLY_VS, PREMULTIPLY_FS)? })
+            }
+            AlphaMode::Ignore | AlphaMode::Straight => None,
+        };
+        Ok(Self { mode, objects })
+    }
+
+    pub fn mode(&self) -> AlphaMode {
+        self.mode
+    }
+
+    /// Premultiplies `rect` of the default framebuffer in place by blending a
+    /// full screen triangle with `dst.rgb * dst.a`. Leaves the viewport set
+    /// to `rect`.
+    pub fn apply(&self, gl: &glow::Context, rect: Rect) {
+        let (program, vao) = match self.objects {
+            Some(objects) => objects,
+            None => return,
+        };
+        unsafe {
+            gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, None);
+            rect.viewport(gl);
+            gl.enable(glow::BLEND);
+            gl.blend_func_separate(glow::ZERO, glow::DST_ALPHA, glow::ZERO, glow::ONE);
+            gl.use_program(Some(program));
+            gl.bind_vertex_array(vao);
+            gl.draw_arrays(glow::TRIANGLES, 0, 3);
+            gl.bind_vertex_array(None);
+            gl.use_program(None);
+            gl.disable(glow::BLEND);
+        }
+    }
+
+    pub fn delete(self, gl: &glow::Context) {
+        if let Some((program, vao)) = self.objects {
+            unsafe {
+                gl.delete_program(program);
+                if let Some(vao) = vao {
+                    gl.delete_vertex_array(vao);
+                }
+            }
+        }
+    }
+}
+
+/// Compiles a program from sources without a `#version` line and, where
+/// supported, creates an empty vertex array to draw it with. Core profiles
+/// need one bound even without attributes.
+unsafe fn program(
+    gl: &glow::Context,
+    caps: &GlCaps,
+    vs: &str,
+    fs: &str,
+) -> Result<(glow::NativeProgram, Option<glow::NativeVertexArray>), String> {
+    let version = caps.glsl_header();
+
+    let program = gl.create_program()?;
+    let mut shaders = Vec::new();
+    for (ty, src) in [(glow::VERTEX_SHADER, vs), (glow::FRAGMENT_SHADER, fs)] {
+        let shader = gl.create_shader(ty)?;
+        gl.shader_source(shader, &format!("{}\n{}", version, src));
+        gl.compile_shader(shader);
+        if !gl.get_shader_compile_status(shader) {
+            let log = gl.get_shader_info_log(shader);
+            for shader in shaders.into_iter().chain([shader]) {
+                gl.delete_shader(shader);
+            }
+            gl.delete_program(program);
+            return Err(log);
+        }
+        gl.attach_shader(program, shader);
+        shaders.push(shader);
+    }
+    gl.link_program(program);
+    for shader in shaders {
+        gl.detach_shader(program, shader);
+        gl.delete_shader(shader);
+    }
+    if !gl.get_program_link_status(program) {
+        let log = gl.get_program_info_log(program);
+        gl.delete_program(program);
+        return Err(log);
+    }
+
+    if !caps.has_vao() {
+        return Ok((program, None));
+    }
+    match gl.create_vertex_array() {
+        Ok(vao) => Ok((program, Some(vao))),
+        Err(err) => {
+            gl.delete_program(program);
+            Err(err)
+        }
+    }
+}
+
+/// Which object the two contexts share for presentation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShareObject {
+    /// Share a renderbuffer and blit from it. Faster, but renderbuffers are not
+    /// in the set of objects every driver shares.
+    Renderbuffer,
+    /// Share a texture and draw it with a full screen quad, which works
+    /// wherever sharing works at all.
+    #[default]
+    Texture,
+}
+
+const QUAD_VS: &str = "
+const vec2 verts[3] = vec2[3](vec2(-1.0, -1.0), vec2(3.0, -1.0), vec2(-1.0, 3.0));
+uniform vec4 uv_rect;
+out vec2 uv;
+void main() {
+    vec2 pos = verts[gl_VertexID];
+    uv = uv_rect.xy + (pos * 0.5 + 0.5) * uv_rect.zw;
+    gl_Position = vec4(pos, 0.0, 1.0);
+}
+";
+const QUAD_FS: &str = "
+precision mediump float;
+uniform sampler2D tex;
+in vec2 uv;
+out vec4 color;
+void main() {
+    color = texture(tex, uv);
+}
+";
+
+const QUAD_SRGB_FS: &str = "
+precision mediump float;
+uniform sampler2D tex;
+in vec2 uv;
+out vec4 color;
+void main() {
+    vec4 linear = texture(tex, uv);
+    vec3 low = linear.rgb * 12.92;
+    vec3 high = 1.055 * pow(linear.rgb, vec3(1.0 / 2.4)) - 0.055;
+    color = vec4(mix(low, high, step(0.0031308, linear.rgb)), linear.a);
+}
+";
+
+/// A texture [`QuadPresenter::draw`] samples, with its full size in texels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceTexture {
+    pub texture: glow::NativeTexture,
+    pub size: (u32, u32),
+}
+
+/// Draws a texture to the window with a full screen quad, the sampling
+/// counterpart of [`blit_to_window`].
+pub struct QuadPresenter {
+    program: glow::NativeProgram,
+    vao: Option<glow::NativeVertexArray>,
+    uv_rect: Option<glow::UniformLocation>,
+}
+
+impl QuadPresenter {
+    pub fn new(gl: &glow::Context, caps: &GlCaps) -> Result<Self, String> {
+        Self::with_fragment_shader(gl, caps, QUAD_FS)
+    }
+
+    /// Like [`Self::new`], but encodes the linear values it samples to sRGB,
+    /// for a linear texture presented to a window without `FRAMEBUFFER_SRGB`.
+    pub fn new_srgb(gl: &glow::Context, caps: &GlCaps) -> Result<Self, String> {
+        Self::with_fragment_shader(gl, caps, QUAD_SRGB_FS)
+    }
+
+    fn with_fragment_shader(gl: &glow::Context, caps: &GlCaps, fs: &str) -> Result<Self, String> {
+        let (program, vao) = unsafe { program(gl, caps, QUAD_VS, fs)? };
+        let uv_rect = unsafe { gl.get_uniform_location(program, "uv_rect") };
+        Ok(Self {
+            program,
+            vao,
+            uv_rect,
+        })
+    }
+
+    /// Draws `src` of `source` to `dst` of the default framebuffer like
+    /// [`blit_to_window`] does, and like it draws nothing if the rects fail
+    /// [`checked_blit_rects`]. Uses texture unit 0 and leaves the viewport set
+    /// to the drawn area.
+    pub fn draw(
+        &self,
+        gl: &glow::Context,
+        source: SourceTexture,
+        src: Rect,
+        dst: Rect,
+        mode: AspectMode,
+        bar_color: [f32; 4],
+    ) -> Result<(), BlitError> {
+        let (src, fitted) = checked_blit_rects(mode, src, dst)?;
+        let (tw, th) = (source.size.0.max(1) as f32, source.size.1.max(1) as f32);
+        unsafe {
+            gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, None);
+            if fitted != dst {
+                let [r, g, b, a] = bar_color;
+                gl.enable(glow::SCISSOR_TEST);
+                dst.scissor(gl);
+                gl.clear_color(r, g, b, a);
+                gl.clear(glow::COLOR_BUFFER_BIT);
+                gl.disable(glow::SCISSOR_TEST);
+            }
+            fitted.viewport(gl);
+            gl.use_program(Some(self.program));
+            gl.uniform_4_f32(
+                self.uv_rect.as_ref(),
+                src.x as f32 / tw,
+                src.y as f32 / th,
+                src.width as f32 / tw,
+                src.height as f32 / th,
+            );
+            gl.active_texture(glow::TEXTURE0);
+            gl.bind_texture(glow::TEXTURE_2D, Some(source.texture));
+            gl.bind_vertex_array(self.vao);
+            gl.draw_arrays(glow::TRIANGLES, 0, 3);
+            gl.bind_vertex_array(None);
+            gl.bind_texture(glow::TEXTURE_2D, None);
+            gl.use_program(None);
+        }
+        Ok(())
+    }
+
+    pub fn delete(self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_program(self.program);
+            if let Some(vao) = self.vao {
+                gl.delete_vertex_array(vao);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_source() {
+        let (src, dst) = (Rect::from_size(0, 10), Rect::from_size(100, 100));
+        assert_eq!(blit_rects(AspectMode::Fit, src, dst), (src, dst));
+        assert_eq!(
+            checked_blit_rects(AspectMode::Fit, src, dst),
+            Err(BlitError::EmptySource(src))
+        );
+    }
+
+    #[test]
+    fn empty_destination() {
+        let (src, dst) = (Rect::from_size(10, 10), Rect::from_size(100, 0));
+        assert_eq!(blit_rects(AspectMode::Fill, src, dst), (src, dst));
+        assert_eq!(
+            checked_blit_rects(AspectMode::Fill, src, dst),
+            Err(BlitError::EmptyDestination(dst))
+        );
+    }
+
+    #[test]
+    fn fit_rounds_to_zero() {
+        let (src, dst) = (Rect::from_size(1000, 1), Rect::from_size(100, 100));
+        assert_eq!(
+            checked_blit_rects(AspectMode::Fit, src, dst),
+            Err(BlitError::EmptyDestination(Rect::new(0, 50, 100, 0)))
+        );
+    }
+
+    #[test]
+    fn stretch() {
+        let (src, dst) = (Rect::from_size(100, 50), Rect::new(10, 20, 200, 200));
+        assert_eq!(
+            checked_blit_rects(AspectMode::Stretch, src, dst),
+            Ok((src, dst))
+        );
+    }
+
+    #[test]
+    fn fit_letterbox() {
+        let (src, dst) = (Rect::from_size(100, 50), Rect::from_size(200, 200));
+        assert_eq!(
+            checked_blit_rects(AspectMode::Fit, src, dst),
+            Ok((src, Rect::new(0, 50, 200, 100)))
+        );
+        // pillarbox, offset by the destination origin
+        let dst = Rect::new(10, 20, 200, 100);
+        let src = Rect::from_size(100, 100);
+        assert_eq!(
+            checked_blit_rects(AspectMode::Fit, src, dst),
+            Ok((src, Rect::new(60, 20, 100, 100)))
+        );
+    }
+
+    #[test]
+    fn fill_crop() {
+        let (src, dst) = (Rect::from_size(100, 100), Rect::from_size(200, 100));
+        assert_eq!(
+            checked_blit_rects(AspectMode::Fill, src, dst),
+            Ok((Rect::new(0, 25, 100, 50), dst))
+        );
+        // offset by the source origin
+        let (src, dst) = (Rect::new(5, 5, 100, 100), Rect::from_size(100, 200));
+        assert_eq!(
+            checked_blit_rects(AspectMode::Fill, src, dst),
+            Ok((Rect::new(30, 5, 50, 100), dst))
+        );
+    }
+}
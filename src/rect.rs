@@ -0,0 +1,80 @@
+use glow::HasContext;
+
+use crate::caps::GlCaps;
+
+/// An axis aligned rectangle in framebuffer coordinates, origin bottom left.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn from_size(width: u32, height: u32) -> Self {
+        Self::new(0, 0, width, height)
+    }
+
+    /// Builds a rect from the `x0, y0, x1, y1` corners used by `glBlitFramebuffer`.
+    pub fn from_corners(x0: i32, y0: i32, x1: i32, y1: i32) -> Self {
+        Self::new(x0.min(x1), y0.min(y1), x0.abs_diff(x1), y0.abs_diff(y1))
+    }
+
+    /// `[x0, y0, x1, y1]` as used by `glBlitFramebuffer`.
+    pub fn corners(self) -> [i32; 4] {
+        [
+            self.x,
+            self.y,
+            self.x + self.width as i32,
+            self.y + self.height as i32,
+        ]
+    }
+
+    pub fn size(self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn viewport(self, gl: &glow::Context) {
+        unsafe { gl.viewport(self.x, self.y, self.width as _, self.height as _) }
+    }
+
+    pub fn scissor(self, gl: &glow::Context) {
+        unsafe { gl.scissor(self.x, self.y, self.width as _, self.height as _) }
+    }
+}
+
+/// Sets viewports `0..n` to `rects` with `glViewportArrayv`, for a geometry
+/// shader picking `gl_ViewportIndex`, and returns `n`. Rects beyond
+/// [`GlCaps::max_viewports`] are dropped; without viewport arrays only the
+/// first rect is set, as the single viewport.
+pub fn set_viewports(gl: &glow::Context, caps: &GlCaps, rects: &[Rect]) -> usize {
+    let first = match rects.first() {
+        Some(rect) => *rect,
+        None => return 0,
+    };
+    if caps.max_viewports() <= 1 {
+        first.viewport(gl);
+        return 1;
+    }
+    let values: Vec<[f32; 4]> = rects
+        .iter()
+        .take(caps.max_viewports() as usize)
+        .map(|r| [r.x as f32, r.y as f32, r.width as f32, r.height as f32])
+        .collect();
+    unsafe { gl.viewport_f32_slice(0, values.len() as i32, &values) };
+    values.len()
+}
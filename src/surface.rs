@@ -0,0 +1,261 @@
+use std::num::NonZeroU32;
+
+use glow::HasContext;
+use glutin::{
+    config::Config,
+    context::{NotCurrentContext, PossiblyCurrentContext},
+    display::Display,
+    error::{ErrorKind, Result},
+    prelude::{GlDisplay, NotCurrentGlContext, NotCurrentGlContextSurfaceAccessor},
+    surface::{
+        GlSurface, PbufferSurface, Surface, SurfaceAttributesBuilder, SurfaceTypeTrait,
+        WindowSurface,
+    },
+};
+use raw_window_handle::RawWindowHandle;
+
+use crate::{context::CreateError, display::create_surface, egl};
+
+pub enum AnySurface {
+    Window(Surface<WindowSurface>),
+    Pbuffer(Surface<PbufferSurface>),
+    Surfaceless,
+}
+
+impl AnySurface {
+    pub fn make_current(&self, ctx: NotCurrentContext) -> Result<PossiblyCurrentContext> {
+        match self {
+            AnySurface::Window(s) => ctx.make_current(s),
+            AnySurface::Pbuffer(s) => ctx.make_current(s),
+            AnySurface::Surfaceless => ctx.make_current_surfaceless(),
+        }
+    }
+
+    pub fn swap_buffers(&self, c: &PossiblyCurrentContext) -> Result<()> {
+        match self {
+            AnySurface::Window(s) => s.swap_buffers(c),
+            AnySurface::Pbuffer(s) => s.swap_buffers(c),
+            AnySurface::Surfaceless => Ok(()),
+        }
+    }
+
+    /// The buffering the surface was granted, [`Buffering::None`] without one.
+    pub fn buffering(&self) -> Buffering {
+        match self {
+            AnySurface::Window(s) => Buffering::of_surface(s),
+            AnySurface::Pbuffer(s) => Buffering::of_surface(s),
+            AnySurface::Surfaceless => Buffering::None,
+        }
+    }
+
+    /// Like [`present`], surfaceless just flushes.
+    pub fn present(&self, c: &PossiblyCurrentContext, gl: &glow::Context) -> Result<()> {
+        match self {
+            AnySurface::Window(s) => present(s, c, gl),
+            AnySurface::Pbuffer(s) => present(s, c, gl),
+            AnySurface::Surfaceless => {
+                unsafe { gl.flush() };
+                Ok(())
+            }
+        }
+    }
+
+    pub fn resize(&self, c: &PossiblyCurrentContext, width: NonZeroU32, height: NonZeroU32) {
+        match self {
+            AnySurface::Window(s) => s.resize(c, width, height),
+            AnySurface::Pbuffer(s) => s.resize(c, width, height),
+            AnySurface::Surfaceless => {}
+        }
+    }
+
+    /// The raw EGL surface, `None` without one or on other backends.
+    pub(crate) fn raw_egl(&self) -> Option<*const std::ffi::c_void> {
+        match self {
+            AnySurface::Window(s) => egl::raw_surface(s),
+            AnySurface::Pbuffer(s) => egl::raw_surface(s),
+            AnySurface::Surfaceless => None,
+        }
+    }
+
+    pub fn kind(&self) -> SurfaceKind {
+        match self {
+            AnySurface::Window(_) => SurfaceKind::Window,
+            AnySurface::Pbuffer(_) => SurfaceKind::Pbuffer,
+            AnySurface::Surfaceless => SurfaceKind::Surfaceless,
+        }
+    }
+
+    pub fn width(&self) -> Option<u32> {
+        match self {
+            AnySurface::Window(s) => s.width(),
+            AnySurface::Pbuffer(s) => s.width(),
+            AnySurface::Surfaceless => None,
+        }
+    }
+
+    pub fn height(&self) -> Option<u32> {
+        match self {
+            AnySurface::Window(s) => s.height(),
+            AnySurface::Pbuffer(s) => s.height(),
+            AnySurface::Surfaceless => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SurfaceKind {
+    Window,
+    Pbuffer,
+    Surfaceless,
+}
+
+/// Properties a surface actually got, see
+/// [`ContextWrapper::surface_info`](crate::ContextWrapper::surface_info).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SurfaceInfo {
+    pub kind: SurfaceKind,
+    pub buffering: Buffering,
+    /// As reported by the surface, 0 without one.
+    pub width: u32,
+    pub height: u32,
+    /// Only window surfaces have a swap interval, pbuffers never wait.
+    pub swap_interval: bool,
+}
+
+/// What presenting the headless surface does, see
+/// [`ContextWrapper::set_headless_present`](crate::ContextWrapper::set_headless_present).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeadlessPresent {
+    /// Nothing, releasing the context flushes it anyway.
+    None,
+    Flush,
+    /// [`present`] the surface like a window, swapping if double buffered.
+    Swap,
+}
+
+impl HeadlessPresent {
+    /// Nothing to do without a surface, a flush otherwise since nobody looks
+    /// at the headless surface itself.
+    pub fn default_for(kind: SurfaceKind) -> Self {
+        match kind {
+            SurfaceKind::Surfaceless => HeadlessPresent::None,
+            SurfaceKind::Window | SurfaceKind::Pbuffer => HeadlessPresent::Flush,
+        }
+    }
+}
+
+/// What presenting a surface can do, see
+/// [`ContextWrapper::present_capabilities`](crate::ContextWrapper::present_capabilities).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PresentCaps {
+    /// Lowest and highest swap interval the config supports, `None` where
+    /// the backend does not report them.
+    pub swap_interval: Option<(u32, u32)>,
+    /// Late swaps tear instead of waiting for the next interval, via
+    /// `GLX_EXT_swap_control_tear`.
+    pub adaptive: bool,
+    pub buffering: Buffering,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Buffering {
+    /// Rendering goes straight to the visible buffer.
+    Single,
+    Double,
+    /// Surfaceless, there is nothing to present.
+    None,
+}
+
+impl Buffering {
+    pub fn of_surface<T: SurfaceTypeTrait>(surface: &Surface<T>) -> Self {
+        if surface.is_single_buffered() {
+            Buffering::Single
+        } else {
+            Buffering::Double
+        }
+    }
+}
+
+/// Presents what was rendered to `surface`: swaps a double buffered surface
+/// and flushes a single buffered one, where a swap does nothing. `c` must be
+/// current with `surface` and `gl` loaded from it.
+pub fn present<T: SurfaceTypeTrait>(
+    surface: &Surface<T>,
+    c: &PossiblyCurrentContext,
+    gl: &glow::Context,
+) -> Result<()> {
+    match Buffering::of_surface(surface) {
+        Buffering::Single => {
+            unsafe { gl.flush() };
+            Ok(())
+        }
+        _ => surface.swap_buffers(c),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeadlessKind {
+    Pbuffer,
+    Surfaceless,
+}
+
+/// What the headless context is made current with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HeadlessSurface {
+    /// No surface at all if the display supports `EGL_KHR_surfaceless_context`,
+    /// otherwise a 1x1 pbuffer, or a 1x1 surface on the window if the config
+    /// has no pbuffer support.
+    #[default]
+    Auto,
+    Force(HeadlessKind),
+}
+
+/// Creates the surface the headless context is made current with. A forced
+/// pbuffer needs a `config` with pbuffer support, forcing surfaceless on a
+/// display without it makes the first `make_current` fail.
+pub fn create_headless_surface(
+    display: &Display,
+    config: &Config,
+    raw_wnd: RawWindowHandle,
+    mode: HeadlessSurface,
+) -> std::result::Result<AnySurface, CreateError> {
+    match mode {
+        HeadlessSurface::Auto => {
+            if egl::has_extension(display, "EGL_KHR_surfaceless_context") {
+                return Ok(AnySurface::Surfaceless);
+            }
+            // some drivers fail to allocate a second surface on the window
+            match create_pbuffer(display, config) {
+                Ok(pbuffer) => Ok(pbuffer.into()),
+                Err(_) => Ok(create_surface(1, 1, display, config, raw_wnd)?.into()),
+            }
+        }
+        HeadlessSurface::Force(HeadlessKind::Pbuffer) => create_pbuffer(display, config)
+            .map(AnySurface::from)
+            .map_err(CreateError::Surface),
+        HeadlessSurface::Force(HeadlessKind::Surfaceless) => Ok(AnySurface::Surfaceless),
+    }
+}
+
+/// A 1x1 pbuffer to make a context current on without a window, `config`
+/// needs pbuffer support.
+pub(crate) fn create_pbuffer(
+    display: &Display,
+    config: &Config,
+) -> Result<Surface<PbufferSurface>> {
+    let one = NonZeroU32::new(1).ok_or(ErrorKind::BadParameter)?;
+    let attrs = SurfaceAttributesBuilder::<PbufferSurface>::new().build(one, one);
+    unsafe { display.create_pbuffer_surface(config, &attrs) }
+}
+
+impl From<Surface<WindowSurface>> for AnySurface {
+    fn from(surface: Surface<WindowSurface>) -> Self {
+        AnySurface::Window(surface)
+    }
+}
+
+impl From<Surface<PbufferSurface>> for AnySurface {
+    fn from(surface: Surface<PbufferSurface>) -> Self {
+        AnySurface::Pbuffer(surface)
+    }
+}
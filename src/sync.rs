@@ -0,0 +1,44 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use glow::HasContext;
+
+/// Blocks until all commands issued on the current context have completed.
+pub fn finish(gl: &glow::Context) {
+    unsafe { gl.finish() }
+}
+
+/// Waits for the commands issued so far on the current context by polling a
+/// fence, sleeping between polls instead of spinning inside the driver like
+/// `glFinish` may. Returns `false` if `timeout` elapsed first.
+///
+/// Falls back to [`finish`] when fences are not supported.
+pub fn wait_idle(gl: &glow::Context, timeout: Duration) -> bool {
+    let fence = match unsafe { gl.fence_sync(glow::SYNC_GPU_COMMANDS_COMPLETE, 0) } {
+        Ok(fence) => fence,
+        Err(_) => {
+            finish(gl);
+            return true;
+        }
+    };
+
+    let deadline = Instant::now() + timeout;
+    let mut flags = glow::SYNC_FLUSH_COMMANDS_BIT;
+    let signaled = loop {
+        match unsafe { gl.client_wait_sync(fence, flags, 0) } {
+            glow::ALREADY_SIGNALED | glow::CONDITION_SATISFIED => break true,
+            glow::WAIT_FAILED => break false,
+            _ => {}
+        }
+        if Instant::now() >= deadline {
+            break false;
+        }
+        flags = 0;
+        thread::sleep(Duration::from_micros(100));
+    };
+
+    unsafe { gl.delete_sync(fence) };
+    signaled
+}
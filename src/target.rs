@@ -0,0 +1,1155 @@
+use glow::HasContext;
+
+use crate::{
+    caps::GlCaps,
+    context::{ContextId, ContextWrapper, GlObject, ObjectError},
+    present::blit,
+    rect::Rect,
+};
+
+/// A renderbuffer or texture shared between the two contexts of a
+/// [`ContextWrapper`], with a framebuffer in each context it is attached to.
+/// The headless context renders into [`Self::headless_fb`], the window context
+/// reads from [`Self::window_fb`].
+///
+/// Methods taking a [`ContextWrapper`] make the contexts current as needed and
+/// expect neither of them to be current when called. A failed make current is
+/// returned, see [`ContextWrapper::try_ct_wnd`].
+pub struct SharedTarget {
+    storage: Storage,
+    window_fb: glow::NativeFramebuffer,
+    headless_fb: glow::NativeFramebuffer,
+    format: u32,
+    window_size: (u32, u32),
+    internal_resolution: Option<(u32, u32)>,
+    /// Factor of the window size and the largest storage size allowed for it.
+    supersample: (f32, u32),
+    sizing: RenderTargetSizing,
+    depth_texture: Option<glow::NativeTexture>,
+    msaa: Option<Msaa>,
+    read_attachment: u32,
+    clear_color: [f32; 4],
+    clear_depth: f32,
+    clear_stencil: i32,
+}
+
+/// Multisampled rendering into a [`SharedTarget`], resolved into its shared
+/// storage after each frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MsaaConfig {
+    /// Clamped to `GL_MAX_SAMPLES`.
+    pub samples: u32,
+    /// Internal format of the shared storage the samples are resolved into,
+    /// `None` for the format the target already has. It has to be blit
+    /// compatible with the format rendered in, see [`check_resolve_format`].
+    pub resolve_format: Option<u32>,
+}
+
+/// How the storage of a [`SharedTarget`] follows its size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderTargetSizing {
+    /// Reallocates the storage whenever the size changes.
+    #[default]
+    Exact,
+    /// Allocates the storage at this size once. Sizes up to it only change
+    /// the rectangle rendered into and blitted from, larger ones are clamped.
+    Fixed(u32, u32),
+}
+
+/// The parameters [`SharedTarget::reconfigure`] changes at runtime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderTargetConfig {
+    /// Internal format rendered in, with MSAA the format of the samples.
+    pub format: u32,
+    pub msaa: Option<MsaaConfig>,
+    /// Whether a depth texture is attached, see
+    /// [`SharedTarget::attach_depth_texture`].
+    pub depth: bool,
+    pub sizing: RenderTargetSizing,
+}
+
+/// The multisampled renderbuffers and framebuffer, all headless only.
+#[derive(Clone, Copy)]
+struct Msaa {
+    rb: glow::NativeRenderbuffer,
+    fb: glow::NativeFramebuffer,
+    /// Multisampled depth, resolved into the depth texture, while one is
+    /// attached.
+    depth: Option<glow::NativeRenderbuffer>,
+    samples: u32,
+    format: u32,
+}
+
+/// Checks that samples rendered in `format` can be resolved into
+/// `resolve_format` with `glBlitFramebuffer`. GLES requires identical formats,
+/// desktop GL only that both are integer formats or neither is.
+pub fn check_resolve_format(format: u32, resolve_format: u32, gles: bool) -> Result<(), String> {
+    if gles && format != resolve_format {
+        return Err(format!(
+            "GLES cannot resolve format 0x{:04X} into a different format 0x{:04X}",
+            format, resolve_format
+        ));
+    }
+    if is_integer_format(format) != is_integer_format(resolve_format) {
+        return Err(format!(
+            "cannot resolve between integer and non-integer formats 0x{:04X} and 0x{:04X}",
+            format, resolve_format
+        ));
+    }
+    Ok(())
+}
+
+fn is_integer_format(format: u32) -> bool {
+    matches!(
+        format,
+        glow::R8I
+            | glow::R8UI
+            | glow::R16I
+            | glow::R16UI
+            | glow::R32I
+            | glow::R32UI
+            | glow::RG8I
+            | glow::RG8UI
+            | glow::RG16I
+            | glow::RG16UI
+            | glow::RG32I
+            | glow::RG32UI
+            | glow::RGBA8I
+            | glow::RGBA8UI
+            | glow::RGBA16I
+            | glow::RGBA16UI
+            | glow::RGBA32I
+            | glow::RGBA32UI
+            | glow::RGB10_A2UI
+    )
+}
+
+#[derive(Clone, Copy)]
+enum Storage {
+    Renderbuffer(glow::NativeRenderbuffer),
+    Texture(glow::NativeTexture),
+    /// A texture owned by other code, never reallocated or deleted here.
+    External(glow::NativeTexture, (u32, u32)),
+}
+
+impl Storage {
+    unsafe fn attach(self, gl: &glow::Context) {
+        match self {
+            Storage::Renderbuffer(rb) => gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::RENDERBUFFER,
+                Some(rb),
+            ),
+            Storage::Texture(tex) | Storage::External(tex, _) => gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(tex),
+                0,
+            ),
+        }
+    }
+
+    /// Deletes storage created by the target, which must be current in the
+    /// window context, and returns the object to untrack.
+    unsafe fn delete(self, gl: &glow::Context) -> Option<GlObject> {
+        match self {
+            Storage::Renderbuffer(rb) => {
+                gl.delete_renderbuffer(rb);
+                Some(GlObject::Renderbuffer(rb))
+            }
+            Storage::Texture(tex) => {
+                gl.delete_texture(tex);
+                Some(GlObject::Texture(tex))
+            }
+            Storage::External(..) => None,
+        }
+    }
+
+    unsafe fn allocate(self, gl: &glow::Context, format: u32, width: u32, height: u32) {
+        match self {
+            Storage::Renderbuffer(rb) => {
+                gl.bind_renderbuffer(glow::RENDERBUFFER, Some(rb));
+                gl.renderbuffer_storage(glow::RENDERBUFFER, format, width as _, height as _);
+            }
+            Storage::Texture(tex) => {
+                let (pixel_format, ty) = pixel_transfer(format);
+                gl.bind_texture(glow::TEXTURE_2D, Some(tex));
+                gl.tex_image_2d(
+                    glow::TEXTURE_2D,
+                    0,
+                    format as _,
+                    width as _,
+                    height as _,
+                    0,
+                    pixel_format,
+                    ty,
+                    None,
+                );
+                gl.bind_texture(glow::TEXTURE_2D, None);
+            }
+            Storage::External(..) => {}
+        }
+    }
+}
+
+/// Format and type to pass along an internal format to `glTexImage2D`, which
+/// GLES requires to match even without any pixel data.
+pub(crate) fn pixel_transfer(internal_format: u32) -> (u32, u32) {
+    match internal_format {
+        glow::RGB8 | glow::SRGB8 => (glow::RGB, glow::UNSIGNED_BYTE),
+        glow::RGB10_A2 => (glow::RGBA, glow::UNSIGNED_INT_2_10_10_10_REV),
+        glow::RGBA16F => (glow::RGBA, glow::HALF_FLOAT),
+        glow::RGB16F => (glow::RGB, glow::HALF_FLOAT),
+        glow::DEPTH_COMPONENT24 => (glow::DEPTH_COMPONENT, glow::UNSIGNED_INT),
+        _ => (glow::RGBA, glow::UNSIGNED_BYTE),
+    }
+}
+
+unsafe fn attach_depth(gl: &glow::Context, fb: glow::NativeFramebuffer, tex: glow::NativeTexture) {
+    gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+    gl.framebuffer_texture_2d(
+        glow::FRAMEBUFFER,
+        glow::DEPTH_ATTACHMENT,
+        glow::TEXTURE_2D,
+        Some(tex),
+        0,
+    );
+    gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+}
+
+unsafe fn detach_depth(gl: &glow::Context, fb: glow::NativeFramebuffer) {
+    gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+    gl.framebuffer_texture_2d(
+        glow::FRAMEBUFFER,
+        glow::DEPTH_ATTACHMENT,
+        glow::TEXTURE_2D,
+        None,
+        0,
+    );
+    gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+}
+
+/// Creates a multisampled `DEPTH_COMPONENT24` renderbuffer and attaches it to
+/// `fb`, the depth the samples of a depth textured target are rendered into.
+unsafe fn attach_msaa_depth(
+    gl: &glow::Context,
+    fb: glow::NativeFramebuffer,
+    samples: u32,
+    (width, height): (u32, u32),
+) -> Result<glow::NativeRenderbuffer, String> {
+    let rb = gl.create_renderbuffer()?;
+    gl.bind_renderbuffer(glow::RENDERBUFFER, Some(rb));
+    gl.renderbuffer_storage_multisample(
+        glow::RENDERBUFFER,
+        samples as i32,
+        glow::DEPTH_COMPONENT24,
+        width as _,
+        height as _,
+    );
+    gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+    gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+    gl.framebuffer_renderbuffer(
+        glow::FRAMEBUFFER,
+        glow::DEPTH_ATTACHMENT,
+        glow::RENDERBUFFER,
+        Some(rb),
+    );
+    gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+    Ok(rb)
+}
+
+unsafe fn framebuffer_status(gl: &glow::Context, fb: glow::NativeFramebuffer) -> u32 {
+    gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+    let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+    gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+    status
+}
+
+impl SharedTarget {
+    /// Creates the target with the size of the window, the objects are deleted
+    /// on [`ContextWrapper::shutdown`].
+    pub fn new(
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        glh: &glow::Context,
+        format: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, ObjectError> {
+        let c = ctx.try_ct_wnd()?;
+        let storage = unsafe { glw.create_renderbuffer() }.map(|rb| {
+            ctx.track_wnd(GlObject::Renderbuffer(rb));
+            Storage::Renderbuffer(rb)
+        });
+        ctx.put_wnd(c)?;
+        Self::with_storage(ctx, glw, glh, storage?, format, width, height)
+    }
+
+    /// Like [`Self::new`] but renders into a texture, which the window context
+    /// can sample in addition to blitting from [`Self::window_fb`]:
+    ///
+    /// ```ignore
+    /// glw.bind_texture(glow::TEXTURE_2D, target.texture());
+    /// ```
+    ///
+    /// The texture uses linear filtering and clamps to the edge. As with the
+    /// blit, the headless context has to flush its rendering before the window
+    /// context samples it.
+    pub fn new_texture(
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        glh: &glow::Context,
+        format: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, ObjectError> {
+        let c = ctx.try_ct_wnd()?;
+        let storage = unsafe {
+            glw.create_texture().map(|tex| {
+                ctx.track_wnd(GlObject::Texture(tex));
+                glw.bind_texture(glow::TEXTURE_2D, Some(tex));
+                for (param, value) in [
+                    (glow::TEXTURE_MIN_FILTER, glow::LINEAR),
+                    (glow::TEXTURE_MAG_FILTER, glow::LINEAR),
+                    (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+                    (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+                ] {
+                    glw.tex_parameter_i32(glow::TEXTURE_2D, param, value as i32);
+                }
+                glw.bind_texture(glow::TEXTURE_2D, None);
+                Storage::Texture(tex)
+            })
+        };
+        ctx.put_wnd(c)?;
+        Self::with_storage(ctx, glw, glh, storage?, format, width, height)
+    }
+
+    fn with_storage(
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        glh: &glow::Context,
+        storage: Storage,
+        format: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, ObjectError> {
+        let c = ctx.try_ct_wnd()?;
+        let window_fb = unsafe {
+            storage.allocate(glw, format, width, height);
+            glw.create_framebuffer().map(|fb| {
+                ctx.track_wnd(GlObject::Framebuffer(fb));
+                glw.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+                storage.attach(glw);
+                glw.bind_framebuffer(glow::FRAMEBUFFER, None);
+                fb
+            })
+        };
+        ctx.put_wnd(c)?;
+
+        let c = ctx.try_ct_head()?;
+        let headless_fb = unsafe {
+            glh.create_framebuffer().map(|fb| {
+                ctx.track_head(GlObject::Framebuffer(fb));
+                glh.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+                storage.attach(glh);
+                glh.bind_framebuffer(glow::FRAMEBUFFER, None);
+                fb
+            })
+        };
+        ctx.put_head(c)?;
+
+        Ok(Self {
+            storage,
+            window_fb: window_fb?,
+            headless_fb: headless_fb?,
+            format,
+            window_size: (width, height),
+            internal_resolution: None,
+            supersample: (1.0, 0),
+            sizing: RenderTargetSizing::Exact,
+            depth_texture: None,
+            msaa: None,
+            read_attachment: 0,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            clear_depth: 1.0,
+            clear_stencil: 0,
+        })
+    }
+
+    /// Uploads a tightly packed `width` x `height` RGBA8 image into the shared
+    /// texture, rows bottom to top, from the window context, which can blit
+    /// or sample it right away. A different size than the current one sets
+    /// it as the internal resolution, reallocating the texture. Fails for
+    /// targets not backed by a texture of their own and for data of the wrong
+    /// length. GLES only converts into an RGBA8 or SRGB8_ALPHA8 target.
+    pub fn upload_texture(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<(), ObjectError> {
+        let tex = match self.storage {
+            Storage::Texture(tex) => tex,
+            _ => {
+                let err = "the target is not backed by its own texture";
+                return Err(ObjectError::Gl(err.to_owned()));
+            }
+        };
+        let expected = width as usize * height as usize * 4;
+        if data.len() != expected {
+            return Err(ObjectError::Gl(format!(
+                "expected {} bytes for {}x{} RGBA8, got {}",
+                expected,
+                width,
+                height,
+                data.len()
+            )));
+        }
+        if self.size() != (width, height) {
+            self.set_internal_resolution(ctx, glw, Some((width, height)))?;
+            if self.size() != (width, height) {
+                return Err(ObjectError::Gl(format!(
+                    "{}x{} exceeds the fixed storage size {:?}",
+                    width,
+                    height,
+                    self.storage_size()
+                )));
+            }
+        }
+
+        let c = ctx.try_ct_wnd()?;
+        unsafe {
+            let alignment = glw.get_parameter_i32(glow::UNPACK_ALIGNMENT);
+            let row_length = glw.get_parameter_i32(glow::UNPACK_ROW_LENGTH);
+            glw.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+            glw.pixel_store_i32(glow::UNPACK_ROW_LENGTH, 0);
+            glw.bind_texture(glow::TEXTURE_2D, Some(tex));
+            glw.tex_sub_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                0,
+                0,
+                width as _,
+                height as _,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(data),
+            );
+            glw.bind_texture(glow::TEXTURE_2D, None);
+            glw.pixel_store_i32(glow::UNPACK_ALIGNMENT, alignment);
+            glw.pixel_store_i32(glow::UNPACK_ROW_LENGTH, row_length);
+        }
+        ctx.put_wnd(c)?;
+        Ok(())
+    }
+
+    /// Internal format of the shared storage.
+    pub fn format(&self) -> u32 {
+        self.format
+    }
+
+    /// Size rendered at: the size of an external render target, the internal
+    /// resolution if set and the supersampled window size otherwise, clamped
+    /// to the storage with [`RenderTargetSizing::Fixed`]. The rendering starts
+    /// at the origin of the storage.
+    pub fn size(&self) -> (u32, u32) {
+        let (width, height) = match (self.storage, self.internal_resolution, self.supersample) {
+            (Storage::External(_, size), ..) => return size,
+            (_, Some(resolution), _) => resolution,
+            (_, None, (factor, max)) if factor > 1.0 => {
+                let (width, height) = self.window_size;
+                let scale = |n: u32| ((n as f32 * factor).round() as u32).min(max);
+                (scale(width), scale(height))
+            }
+            _ => self.window_size,
+        };
+        match self.sizing {
+            RenderTargetSizing::Exact => (width, height),
+            RenderTargetSizing::Fixed(max_w, max_h) => (width.min(max_w), height.min(max_h)),
+        }
+    }
+
+    /// Size of the storage, which is [`Self::size`] unless it is fixed.
+    pub fn storage_size(&self) -> (u32, u32) {
+        match (self.storage, self.sizing) {
+            (Storage::External(_, size), _) => size,
+            (_, RenderTargetSizing::Fixed(width, height)) => (width, height),
+            (_, RenderTargetSizing::Exact) => self.size(),
+        }
+    }
+
+    /// Switches between reallocating the storage on every size change and
+    /// allocating it once, reallocating now if the storage size changes.
+    pub fn set_sizing(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        sizing: RenderTargetSizing,
+    ) -> glutin::error::Result<()> {
+        let old = self.storage_size();
+        self.sizing = sizing;
+        if self.storage_size() != old {
+            self.allocate(ctx, glw)?;
+        }
+        Ok(())
+    }
+
+    /// The storage sizing, see [`Self::set_sizing`].
+    pub fn sizing(&self) -> RenderTargetSizing {
+        self.sizing
+    }
+
+    /// Renders into `texture`, owned by other code in the same share group,
+    /// instead of storage allocated here. The storage allocated so far is
+    /// deleted and the texture is attached to both framebuffers. It keeps its
+    /// size across resizes and is never deleted by the target.
+    pub fn set_external_render_target(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        glh: &glow::Context,
+        texture: glow::NativeTexture,
+        width: u32,
+        height: u32,
+    ) -> glutin::error::Result<()> {
+        let old = self.storage;
+        self.storage = Storage::External(texture, (width, height));
+
+        let c = ctx.try_ct_wnd()?;
+        unsafe {
+            glw.bind_framebuffer(glow::FRAMEBUFFER, Some(self.window_fb));
+            self.storage.attach(glw);
+            glw.bind_framebuffer(glow::FRAMEBUFFER, None);
+            if let Some(obj) = old.delete(glw) {
+                ctx.untrack(ContextId::Window, obj);
+            }
+        }
+        ctx.put_wnd(c)?;
+
+        let c = ctx.try_ct_head()?;
+        unsafe {
+            glh.bind_framebuffer(glow::FRAMEBUFFER, Some(self.headless_fb));
+            self.storage.attach(glh);
+            glh.bind_framebuffer(glow::FRAMEBUFFER, None);
+        }
+        ctx.put_head(c)?;
+        if self.depth_texture.is_some() {
+            self.allocate(ctx, glw)?;
+        }
+        Ok(())
+    }
+
+    /// Adds a `DEPTH_COMPONENT24` texture as depth attachment of both
+    /// framebuffers, e.g. for a shadow map rendered by the headless context
+    /// and sampled by the window context, and returns it. It follows the size
+    /// of the target and is deleted with it; calling this again returns the
+    /// same texture. With [`Self::set_msaa`] active, depth is rendered into a
+    /// multisampled buffer and resolved into the texture by [`Self::resolve`].
+    ///
+    /// The texture filters nearest, clamps to the edge and compares against
+    /// the reference with `LEQUAL`, so it is sampled with a shadow sampler.
+    /// Reset `TEXTURE_COMPARE_MODE` to read raw depth values instead.
+    pub fn attach_depth_texture(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        glh: &glow::Context,
+    ) -> Result<glow::NativeTexture, ObjectError> {
+        if let Some(tex) = self.depth_texture {
+            return Ok(tex);
+        }
+        let (width, height) = self.storage_size();
+        let c = ctx.try_ct_wnd()?;
+        let depth = unsafe {
+            glw.create_texture().map(|tex| {
+                ctx.track_wnd(GlObject::Texture(tex));
+                glw.bind_texture(glow::TEXTURE_2D, Some(tex));
+                for (param, value) in [
+                    (glow::TEXTURE_MIN_FILTER, glow::NEAREST),
+                    (glow::TEXTURE_MAG_FILTER, glow::NEAREST),
+                    (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+                    (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+                    (glow::TEXTURE_COMPARE_MODE, glow::COMPARE_REF_TO_TEXTURE),
+                    (glow::TEXTURE_COMPARE_FUNC, glow::LEQUAL),
+                ] {
+                    glw.tex_parameter_i32(glow::TEXTURE_2D, param, value as i32);
+                }
+                glw.bind_texture(glow::TEXTURE_2D, None);
+                Storage::Texture(tex).allocate(glw, glow::DEPTH_COMPONENT24, width, height);
+                attach_depth(glw, self.window_fb, tex);
+                tex
+            })
+        };
+        ctx.put_wnd(c)?;
+        let tex = depth?;
+
+        // with MSAA the samples need a depth buffer of their own
+        let size = self.storage_size();
+        let c = ctx.try_ct_head()?;
+        let msaa_depth = unsafe {
+            attach_depth(glh, self.headless_fb, tex);
+            match &mut self.msaa {
+                Some(msaa) => attach_msaa_depth(glh, msaa.fb, msaa.samples, size).map(|rb| {
+                    ctx.track_head(GlObject::Renderbuffer(rb));
+                    msaa.depth = Some(rb);
+                }),
+                None => Ok(()),
+            }
+        };
+        ctx.put_head(c)?;
+
+        self.depth_texture = Some(tex);
+        msaa_depth?;
+        Ok(tex)
+    }
+
+    /// The texture added by [`Self::attach_depth_texture`].
+    pub fn depth_texture(&self) -> Option<glow::NativeTexture> {
+        self.depth_texture
+    }
+
+    /// Detaches the texture added by [`Self::attach_depth_texture`] from both
+    /// framebuffers and deletes it, along with the multisampled depth.
+    pub fn detach_depth_texture(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        glh: &glow::Context,
+    ) -> glutin::error::Result<()> {
+        let tex = match self.depth_texture {
+            Some(tex) => tex,
+            None => return Ok(()),
+        };
+        let c = ctx.try_ct_head()?;
+        unsafe {
+            detach_depth(glh, self.headless_fb);
+            if let Some(msaa) = &mut self.msaa {
+                if let Some(rb) = msaa.depth.take() {
+                    glh.bind_framebuffer(glow::FRAMEBUFFER, Some(msaa.fb));
+                    glh.framebuffer_renderbuffer(
+                        glow::FRAMEBUFFER,
+                        glow::DEPTH_ATTACHMENT,
+                        glow::RENDERBUFFER,
+                        None,
+                    );
+                    glh.bind_framebuffer(glow::FRAMEBUFFER, None);
+                    glh.delete_renderbuffer(rb);
+                    ctx.untrack(ContextId::Headless, GlObject::Renderbuffer(rb));
+                }
+            }
+        }
+        ctx.put_head(c)?;
+
+        let c = ctx.try_ct_wnd()?;
+        unsafe {
+            detach_depth(glw, self.window_fb);
+            glw.delete_texture(tex);
+        }
+        ctx.untrack(ContextId::Window, GlObject::Texture(tex));
+        ctx.put_wnd(c)?;
+        self.depth_texture = None;
+        Ok(())
+    }
+
+    /// The current parameters, as [`Self::reconfigure`] takes them.
+    pub fn config(&self) -> RenderTargetConfig {
+        RenderTargetConfig {
+            format: self.msaa.map_or(self.format, |msaa| msaa.format),
+            msaa: self.msaa.map(|msaa| MsaaConfig {
+                samples: msaa.samples,
+                resolve_format: Some(self.format),
+            }),
+            depth: self.depth_texture.is_some(),
+            sizing: self.sizing,
+        }
+    }
+
+    /// Applies `config` without touching the contexts or surfaces, e.g. from
+    /// a settings menu: the storage is reallocated in the new format, the
+    /// depth texture attached or deleted and the multisampled renderbuffer
+    /// recreated, then both framebuffers are checked for completeness. An
+    /// external render target keeps its format.
+    pub fn reconfigure(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        glh: &glow::Context,
+        caps: &GlCaps,
+        config: RenderTargetConfig,
+    ) -> Result<(), ObjectError> {
+        if let Storage::External(..) = self.storage {
+            if config.format != self.config().format {
+                let err = "an external render target keeps its format";
+                return Err(ObjectError::Gl(err.to_owned()));
+            }
+        }
+        self.set_msaa(ctx, glw, glh, caps, None)?;
+        self.set_sizing(ctx, glw, config.sizing)?;
+        if config.format != self.format {
+            self.format = config.format;
+            self.allocate(ctx, glw)?;
+        }
+        match (config.depth, self.depth_texture) {
+            (true, None) => {
+                self.attach_depth_texture(ctx, glw, glh)?;
+            }
+            (false, Some(_)) => self.detach_depth_texture(ctx, glw, glh)?,
+            _ => {}
+        }
+        self.set_msaa(ctx, glw, glh, caps, config.msaa)?;
+
+        let c = ctx.try_ct_wnd()?;
+        let window = unsafe { framebuffer_status(glw, self.window_fb) };
+        ctx.put_wnd(c)?;
+        let c = ctx.try_ct_head()?;
+        let headless = unsafe { framebuffer_status(glh, self.render_fb()) };
+        ctx.put_head(c)?;
+        for (name, status) in [("window", window), ("headless", headless)] {
+            if status != glow::FRAMEBUFFER_COMPLETE {
+                let err = format!("{} framebuffer incomplete: 0x{:04X}", name, status);
+                return Err(ObjectError::Gl(err));
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders multisampled into a headless only renderbuffer and resolves it
+    /// into the shared storage with [`Self::resolve`]. The samples are rendered
+    /// in the current format of the target, a different `resolve_format`
+    /// reallocates the shared storage in it. `None` goes back to rendering
+    /// straight into the storage. With a depth texture attached the samples
+    /// get a multisampled depth buffer, which is resolved into the texture.
+    pub fn set_msaa(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        glh: &glow::Context,
+        caps: &GlCaps,
+        config: Option<MsaaConfig>,
+    ) -> Result<(), ObjectError> {
+        let format = self.msaa.map_or(self.format, |msaa| msaa.format);
+        if let Some(MsaaConfig {
+            resolve_format: Some(resolve_format),
+            ..
+        }) = config
+        {
+            check_resolve_format(format, resolve_format, caps.is_gles())?;
+        }
+
+        if let Some(msaa) = self.msaa.take() {
+            let c = ctx.try_ct_head()?;
+            unsafe {
+                glh.delete_framebuffer(msaa.fb);
+                glh.delete_renderbuffer(msaa.rb);
+                if let Some(rb) = msaa.depth {
+                    glh.delete_renderbuffer(rb);
+                    ctx.untrack(ContextId::Headless, GlObject::Renderbuffer(rb));
+                }
+            }
+            ctx.untrack(ContextId::Headless, GlObject::Framebuffer(msaa.fb));
+            ctx.untrack(ContextId::Headless, GlObject::Renderbuffer(msaa.rb));
+            ctx.put_head(c)?;
+        }
+        let config = match config {
+            Some(config) if config.samples > 1 => config,
+            _ => {
+                if format != self.format {
+                    self.format = format;
+                    self.allocate(ctx, glw)?;
+                }
+                return Ok(());
+            }
+        };
+
+        let (width, height) = self.storage_size();
+        let samples = config.samples.min(caps.max_samples().max(1));
+        let depth = self.depth_texture.is_some();
+        let c = ctx.try_ct_head()?;
+        let msaa = unsafe {
+            glh.create_renderbuffer().and_then(|rb| {
+                ctx.track_head(GlObject::Renderbuffer(rb));
+                let fb = glh.create_framebuffer()?;
+                ctx.track_head(GlObject::Framebuffer(fb));
+                glh.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+                glh.bind_renderbuffer(glow::RENDERBUFFER, Some(rb));
+                glh.renderbuffer_storage_multisample(
+                    glow::RENDERBUFFER,
+                    samples as i32,
+                    format,
+                    width as _,
+                    height as _,
+                );
+                glh.framebuffer_renderbuffer(
+                    glow::FRAMEBUFFER,
+                    glow::COLOR_ATTACHMENT0,
+                    glow::RENDERBUFFER,
+                    Some(rb),
+                );
+                glh.bind_framebuffer(glow::FRAMEBUFFER, None);
+                let depth = if depth {
+                    let rb = attach_msaa_depth(glh, fb, samples, (width, height))?;
+                    ctx.track_head(GlObject::Renderbuffer(rb));
+                    Some(rb)
+                } else {
+                    None
+                };
+                Ok(Msaa {
+                    rb,
+                    fb,
+                    depth,
+                    samples,
+                    format,
+                })
+            })
+        };
+        ctx.put_head(c)?;
+        self.msaa = Some(msaa?);
+        let resolve_format = config.resolve_format.unwrap_or(format);
+        if resolve_format != self.format {
+            self.format = resolve_format;
+            self.allocate(ctx, glw)?;
+        }
+        Ok(())
+    }
+
+    /// Framebuffer the headless context renders into: the multisampled one
+    /// if [`Self::set_msaa`] is active, otherwise [`Self::headless_fb`].
+    pub fn render_fb(&self) -> glow::NativeFramebuffer {
+        self.msaa.map_or(self.headless_fb, |msaa| msaa.fb)
+    }
+
+    /// Resolves the multisampled rendering into the shared storage, and its
+    /// depth into the depth texture if attached, nothing without MSAA. The
+    /// headless context must be current, the framebuffer bindings are reset.
+    pub fn resolve(&self, glh: &glow::Context) {
+        let msaa = match self.msaa {
+            Some(msaa) => msaa,
+            None => return,
+        };
+        let rect = Rect::from_size(self.size().0, self.size().1);
+        unsafe {
+            glh.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(msaa.fb));
+            glh.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(self.headless_fb));
+        }
+        blit(glh, rect, rect);
+        if msaa.depth.is_some() {
+            let [x0, y0, x1, y1] = rect.corners();
+            unsafe {
+                glh.blit_framebuffer(
+                    x0,
+                    y0,
+                    x1,
+                    y1,
+                    x0,
+                    y0,
+                    x1,
+                    y1,
+                    glow::DEPTH_BUFFER_BIT,
+                    glow::NEAREST,
+                );
+            }
+        }
+        unsafe { glh.bind_framebuffer(glow::FRAMEBUFFER, None) };
+    }
+
+    /// Samples per pixel of the multisampled rendering, 0 without MSAA.
+    pub fn msaa_samples(&self) -> u32 {
+        self.msaa.map_or(0, |msaa| msaa.samples)
+    }
+
+    /// Makes the blit from [`Self::window_fb`] read `COLOR_ATTACHMENT0 + index`,
+    /// e.g. to cycle through G-buffer targets. The read buffer is state of the
+    /// framebuffer, so it sticks until changed. Fails if the index is beyond
+    /// `GL_MAX_COLOR_ATTACHMENTS`; an index without anything attached makes
+    /// the blit fail with `INVALID_OPERATION` instead.
+    pub fn present_attachment(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        index: u32,
+    ) -> Result<(), ObjectError> {
+        let c = ctx.try_ct_wnd()?;
+        let max = unsafe { glw.get_parameter_i32(glow::MAX_COLOR_ATTACHMENTS) } as u32;
+        if index < max {
+            unsafe {
+                glw.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.window_fb));
+                glw.read_buffer(glow::COLOR_ATTACHMENT0 + index);
+                glw.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+            }
+            self.read_attachment = index;
+        }
+        ctx.put_wnd(c)?;
+        if index >= max {
+            return Err(ObjectError::Gl(format!(
+                "color attachment {} is beyond the {} supported",
+                index, max
+            )));
+        }
+        Ok(())
+    }
+
+    /// Index of the color attachment the blit reads, see
+    /// [`Self::present_attachment`].
+    pub fn read_attachment(&self) -> u32 {
+        self.read_attachment
+    }
+
+    /// Updates the window size, reallocating the storage unless an
+    /// internal resolution is set or the storage size is fixed.
+    pub fn resize(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        width: u32,
+        height: u32,
+    ) -> glutin::error::Result<()> {
+        let old = self.storage_size();
+        self.window_size = (width, height);
+        if self.storage_size() != old {
+            self.allocate(ctx, glw)?;
+        }
+        Ok(())
+    }
+
+    /// Renders at a fixed resolution regardless of the window size, the blit
+    /// stretches it to the window. `None` goes back to matching the window.
+    pub fn set_internal_resolution(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        resolution: Option<(u32, u32)>,
+    ) -> glutin::error::Result<()> {
+        let old = self.storage_size();
+        self.internal_resolution = resolution;
+        if self.storage_size() != old {
+            self.allocate(ctx, glw)?;
+        }
+        Ok(())
+    }
+
+    /// Renders at `factor` times the window size, which the blit scales down
+    /// with linear filtering for supersampling. Factors below 1 are treated as
+    /// 1, and the storage is capped at `GL_MAX_RENDERBUFFER_SIZE`, or
+    /// `GL_MAX_TEXTURE_SIZE` for a texture, per side. An internal resolution
+    /// takes precedence. Returns the factor the current window size gets.
+    pub fn set_supersample(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        factor: f32,
+    ) -> glutin::error::Result<f32> {
+        let param = match self.storage {
+            Storage::Renderbuffer(_) => glow::MAX_RENDERBUFFER_SIZE,
+            _ => glow::MAX_TEXTURE_SIZE,
+        };
+        let c = ctx.try_ct_wnd()?;
+        let max = unsafe { glw.get_parameter_i32(param) }.max(1) as u32;
+        ctx.put_wnd(c)?;
+
+        let old = self.storage_size();
+        self.supersample = (factor.max(1.0), max);
+        if self.storage_size() != old {
+            self.allocate(ctx, glw)?;
+        }
+        let (width, height) = self.window_size;
+        let largest = width.max(height).max(1) as f32;
+        Ok(factor.max(1.0).min(max as f32 / largest))
+    }
+
+    pub fn set_clear_color(&mut self, color: [f32; 4]) {
+        self.clear_color = color;
+    }
+
+    pub fn set_clear_depth(&mut self, depth: f32) {
+        self.clear_depth = depth;
+    }
+
+    pub fn set_clear_stencil(&mut self, stencil: i32) {
+        self.clear_stencil = stencil;
+    }
+
+    /// `glClear` mask covering the attachments of the bound framebuffer:
+    /// color, depth with the depth texture or any other depth attachment, and
+    /// stencil if a stencil buffer is attached, e.g. by the caller. The
+    /// headless context must be current with [`Self::render_fb`] bound.
+    pub fn clear_mask(&self, glh: &glow::Context) -> u32 {
+        let attached = |attachment| unsafe {
+            glh.get_framebuffer_attachment_parameter_i32(
+                glow::FRAMEBUFFER,
+                attachment,
+                glow::FRAMEBUFFER_ATTACHMENT_OBJECT_TYPE,
+            ) as u32
+                != glow::NONE
+        };
+        let mut mask = glow::COLOR_BUFFER_BIT;
+        if self.depth_texture.is_some() || attached(glow::DEPTH_ATTACHMENT) {
+            mask |= glow::DEPTH_BUFFER_BIT;
+        }
+        if attached(glow::STENCIL_ATTACHMENT) {
+            mask |= glow::STENCIL_BUFFER_BIT;
+        }
+        mask
+    }
+
+    /// Clears every attachment of the bound framebuffer, see
+    /// [`Self::clear_mask`], to the configured values. The headless context
+    /// must be current with [`Self::render_fb`] bound, scissoring and write
+    /// masks are left to the caller.
+    pub fn clear(&self, glh: &glow::Context) {
+        let [r, g, b, a] = self.clear_color;
+        unsafe {
+            glh.clear_color(r, g, b, a);
+            glh.clear_depth_f32(self.clear_depth);
+            glh.clear_stencil(self.clear_stencil);
+            glh.clear(self.clear_mask(glh));
+        }
+    }
+
+    /// Clears only `rect` of the color attachment to `color`, leaving depth and
+    /// stencil alone. Same requirements as [`Self::clear`], the scissor test
+    /// is disabled afterwards.
+    pub fn clear_rect(&self, glh: &glow::Context, rect: Rect, color: [f32; 4]) {
+        let [r, g, b, a] = color;
+        unsafe {
+            glh.enable(glow::SCISSOR_TEST);
+            rect.scissor(glh);
+            glh.clear_color(r, g, b, a);
+            glh.clear(glow::COLOR_BUFFER_BIT);
+            glh.disable(glow::SCISSOR_TEST);
+        }
+    }
+
+    fn allocate(&self, ctx: &mut ContextWrapper, glw: &glow::Context) -> glutin::error::Result<()> {
+        let (width, height) = self.storage_size();
+        let c = ctx.try_ct_wnd()?;
+        unsafe {
+            self.storage.allocate(glw, self.format, width, height);
+            if let Some(tex) = self.depth_texture {
+                Storage::Texture(tex).allocate(glw, glow::DEPTH_COMPONENT24, width, height);
+            }
+        }
+        ctx.put_wnd(c)?;
+
+        if let Some(msaa) = self.msaa {
+            let c = ctx.try_ct_head()?;
+            let glh = ctx.gl(ContextId::Headless);
+            unsafe {
+                let depth = msaa.depth.map(|rb| (rb, glow::DEPTH_COMPONENT24));
+                for (rb, format) in [(msaa.rb, msaa.format)].into_iter().chain(depth) {
+                    glh.bind_renderbuffer(glow::RENDERBUFFER, Some(rb));
+                    glh.renderbuffer_storage_multisample(
+                        glow::RENDERBUFFER,
+                        msaa.samples as i32,
+                        format,
+                        width as _,
+                        height as _,
+                    );
+                }
+                glh.bind_renderbuffer(glow::RENDERBUFFER, None);
+            }
+            ctx.put_head(c)?;
+        }
+        Ok(())
+    }
+
+    /// The shared renderbuffer, `None` for a target created with
+    /// [`Self::new_texture`] or rendering to an external target.
+    pub fn renderbuffer(&self) -> Option<glow::NativeRenderbuffer> {
+        match self.storage {
+            Storage::Renderbuffer(rb) => Some(rb),
+            Storage::Texture(_) | Storage::External(..) => None,
+        }
+    }
+
+    /// The shared texture, including an external one, `None` for a target
+    /// created with [`Self::new`].
+    pub fn texture(&self) -> Option<glow::NativeTexture> {
+        match self.storage {
+            Storage::Texture(tex) | Storage::External(tex, _) => Some(tex),
+            Storage::Renderbuffer(_) => None,
+        }
+    }
+
+    /// Framebuffer object of the window context.
+    pub fn window_fb(&self) -> glow::NativeFramebuffer {
+        self.window_fb
+    }
+
+    /// Framebuffer object of the headless context.
+    pub fn headless_fb(&self) -> glow::NativeFramebuffer {
+        self.headless_fb
+    }
+}
+
+/// A renderbuffer and framebuffer in the window context alone, for rendering
+/// offscreen without any sharing. The window context renders into
+/// [`Self::fb`] and blits from it to its own default framebuffer.
+pub struct LocalTarget {
+    rb: glow::NativeRenderbuffer,
+    fb: glow::NativeFramebuffer,
+    format: u32,
+    size: (u32, u32),
+}
+
+impl LocalTarget {
+    /// Creates the target in the window context, the objects are deleted on
+    /// [`ContextWrapper::shutdown`].
+    pub fn new(
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        format: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, ObjectError> {
+        let c = ctx.try_ct_wnd()?;
+        let target = unsafe {
+            glw.create_renderbuffer().and_then(|rb| {
+                ctx.track_wnd(GlObject::Renderbuffer(rb));
+                Storage::Renderbuffer(rb).allocate(glw, format, width, height);
+                let fb = glw.create_framebuffer()?;
+                ctx.track_wnd(GlObject::Framebuffer(fb));
+                glw.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+                Storage::Renderbuffer(rb).attach(glw);
+                glw.bind_framebuffer(glow::FRAMEBUFFER, None);
+                Ok(Self {
+                    rb,
+                    fb,
+                    format,
+                    size: (width, height),
+                })
+            })
+        };
+        ctx.put_wnd(c)?;
+        target.map_err(ObjectError::Gl)
+    }
+
+    /// Reallocates the renderbuffer if the size changed.
+    pub fn resize(
+        &mut self,
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        width: u32,
+        height: u32,
+    ) -> glutin::error::Result<()> {
+        if self.size == (width, height) {
+            return Ok(());
+        }
+        let c = ctx.try_ct_wnd()?;
+        unsafe { Storage::Renderbuffer(self.rb).allocate(glw, self.format, width, height) };
+        ctx.put_wnd(c)?;
+        self.size = (width, height);
+        Ok(())
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    pub fn fb(&self) -> glow::NativeFramebuffer {
+        self.fb
+    }
+}
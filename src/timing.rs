@@ -0,0 +1,184 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use glow::HasContext;
+
+use crate::{
+    caps::GlCaps,
+    context::{ContextId, ContextWrapper, GlObject, ObjectError},
+};
+
+/// Measures the GPU time spent between [`Self::begin`] and [`Self::end`] with
+/// `GL_TIME_ELAPSED` queries. Queries are not shared between contexts, so each
+/// context needs its own timer.
+///
+/// Two queries are used in turn and a result is only read once available,
+/// usually a frame later, so reading never stalls the pipeline unless the GPU
+/// is more than a frame behind.
+pub struct GpuTimer {
+    queries: [glow::NativeQuery; 2],
+    pending: [bool; 2],
+    index: usize,
+    last: Option<Duration>,
+}
+
+impl GpuTimer {
+    /// Creates the queries in context `id`, they are deleted on
+    /// [`ContextWrapper::shutdown`]. Fails if the context has no timer queries.
+    pub fn new(
+        ctx: &mut ContextWrapper,
+        id: ContextId,
+        gl: &glow::Context,
+        caps: &GlCaps,
+    ) -> Result<Self, ObjectError> {
+        let c = match id {
+            ContextId::Window => ctx.try_ct_wnd()?,
+            ContextId::Headless => ctx.try_ct_head()?,
+        };
+        let queries = if caps.has_timer_query() {
+            unsafe { gl.create_query().and_then(|a| Ok([a, gl.create_query()?])) }
+        } else {
+            Err("timer queries are not supported".to_owned())
+        };
+        if let Ok(queries) = &queries {
+            for query in queries {
+                match id {
+                    ContextId::Window => ctx.track_wnd(GlObject::Query(*query)),
+                    ContextId::Headless => ctx.track_head(GlObject::Query(*query)),
+                }
+            }
+        }
+        match id {
+            ContextId::Window => ctx.put_wnd(c)?,
+            ContextId::Headless => ctx.put_head(c)?,
+        }
+
+        Ok(Self {
+            queries: queries?,
+            pending: [false; 2],
+            index: 0,
+            last: None,
+        })
+    }
+
+    pub fn begin(&mut self, gl: &glow::Context) {
+        self.collect(gl, 1 - self.index, false);
+        // a query still pending from two frames ago has to be read before reuse
+        self.collect(gl, self.index, true);
+        unsafe { gl.begin_query(glow::TIME_ELAPSED, self.queries[self.index]) };
+    }
+
+    pub fn end(&mut self, gl: &glow::Context) {
+        unsafe { gl.end_query(glow::TIME_ELAPSED) };
+        self.pending[self.index] = true;
+        self.index = 1 - self.index;
+    }
+
+    /// The most recent measurement that has been read back.
+    pub fn last(&self) -> Option<Duration> {
+        self.last
+    }
+
+    fn collect(&mut self, gl: &glow::Context, index: usize, wait: bool) {
+        if !self.pending[index] {
+            return;
+        }
+        let query = self.queries[index];
+        let available =
+            wait || unsafe { gl.get_query_parameter_u32(query, glow::QUERY_RESULT_AVAILABLE) } != 0;
+        if available {
+            let ns = unsafe { gl.get_query_parameter_u32(query, glow::QUERY_RESULT) };
+            self.last = Some(Duration::from_nanos(ns as u64));
+            self.pending[index] = false;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GpuTimes {
+    /// Offscreen rendering in the headless context.
+    pub render: Duration,
+    /// Blit to the window in the window context.
+    pub blit: Duration,
+}
+
+/// A [`GpuTimer`] for each side of the shared pipeline.
+pub struct FrameTimers {
+    pub render: GpuTimer,
+    pub blit: GpuTimer,
+}
+
+impl FrameTimers {
+    pub fn new(
+        ctx: &mut ContextWrapper,
+        glw: &glow::Context,
+        glh: &glow::Context,
+        caps_w: &GlCaps,
+        caps_h: &GlCaps,
+    ) -> Result<Self, ObjectError> {
+        Ok(Self {
+            render: GpuTimer::new(ctx, ContextId::Headless, glh, caps_h)?,
+            blit: GpuTimer::new(ctx, ContextId::Window, glw, caps_w)?,
+        })
+    }
+
+    /// Latest GPU times, `None` until both timers have a result.
+    pub fn last_gpu_times(&self) -> Option<GpuTimes> {
+        Some(GpuTimes {
+            render: self.render.last()?,
+            blit: self.blit.last()?,
+        })
+    }
+}
+
+/// Frame number and animation time of an app, for animating by frame rather
+/// than by wall clock, e.g. in golden image tests. The app advances it right
+/// before each frame is rendered, see
+/// [`AppBuilder::frame_clock`](crate::AppBuilder::frame_clock). Clones share
+/// the same clock.
+#[derive(Clone, Debug, Default)]
+pub struct FrameClock(Rc<RefCell<ClockState>>);
+
+#[derive(Debug, Default)]
+struct ClockState {
+    frame_index: u64,
+    elapsed: Duration,
+    last_tick: Option<Instant>,
+    fixed_timestep: Option<Duration>,
+}
+
+impl FrameClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index of the frame being rendered, 0 for the first one.
+    pub fn frame_index(&self) -> u64 {
+        self.0.borrow().frame_index
+    }
+
+    /// Animation time at the frame being rendered, 0 for the first one.
+    pub fn elapsed(&self) -> Duration {
+        self.0.borrow().elapsed
+    }
+
+    /// Advances [`Self::elapsed`] by `step` per frame instead of the real
+    /// time between frames, `None` goes back to real time.
+    pub fn set_fixed_timestep(&self, step: Option<Duration>) {
+        self.0.borrow_mut().fixed_timestep = step;
+    }
+
+    /// Moves on to the next frame, the first call only starts the clock.
+    pub fn tick(&self) {
+        let mut state = self.0.borrow_mut();
+        let now = Instant::now();
+        if let Some(last) = state.last_tick {
+            state.frame_index += 1;
+            state.elapsed += state.fixed_timestep.unwrap_or(now - last);
+        }
+        state.last_tick = Some(now);
+    }
+}
@@ -0,0 +1,126 @@
+use glow::HasContext;
+
+use crate::context::{ContextId, ContextWrapper, GlObject, ObjectError, SharingDiagnostic};
+
+/// A uniform buffer shared by the contexts of a [`ContextWrapper`]. Unlike
+/// renderbuffers, buffer objects are shared by every implementation, so the
+/// same buffer can carry per-frame data from the headless to the window
+/// context or back.
+///
+/// The buffer object is shared, binding points are not: each context calls
+/// [`Self::bind`] before its shaders read the buffer. As with any shared
+/// object, the updating context has to flush before another context draws
+/// with the new contents.
+pub struct SharedUniformBuffer {
+    buffer: glow::NativeBuffer,
+    binding: u32,
+    size: usize,
+}
+
+impl SharedUniformBuffer {
+    /// Creates a `size` bytes buffer in the headless context, deleted on
+    /// [`ContextWrapper::shutdown`]. `binding` is the uniform block binding
+    /// the shaders use.
+    pub fn new(
+        ctx: &mut ContextWrapper,
+        glh: &glow::Context,
+        binding: u32,
+        size: usize,
+    ) -> Result<Self, ObjectError> {
+        let c = ctx.try_ct_head()?;
+        let buffer = unsafe {
+            glh.create_buffer().map(|buffer| {
+                ctx.track_head(GlObject::Buffer(buffer));
+                glh.bind_buffer(glow::UNIFORM_BUFFER, Some(buffer));
+                glh.buffer_data_size(glow::UNIFORM_BUFFER, size as i32, glow::DYNAMIC_DRAW);
+                glh.bind_buffer(glow::UNIFORM_BUFFER, None);
+                buffer
+            })
+        };
+        ctx.put_head(c)?;
+        Ok(Self {
+            buffer: buffer?,
+            binding,
+            size,
+        })
+    }
+
+    /// Writes `data` to the start of the buffer through `gl`, whose context
+    /// must be current. Fails if `data` is larger than the buffer.
+    pub fn update(&self, gl: &glow::Context, data: &[u8]) -> Result<(), String> {
+        if data.len() > self.size {
+            return Err(format!(
+                "{} bytes exceed the {} byte uniform buffer",
+                data.len(),
+                self.size
+            ));
+        }
+        unsafe {
+            gl.bind_buffer(glow::UNIFORM_BUFFER, Some(self.buffer));
+            gl.buffer_sub_data_u8_slice(glow::UNIFORM_BUFFER, 0, data);
+            gl.bind_buffer(glow::UNIFORM_BUFFER, None);
+        }
+        Ok(())
+    }
+
+    /// Binds the buffer to [`Self::binding`] in the current context.
+    pub fn bind(&self, gl: &glow::Context) {
+        unsafe { gl.bind_buffer_base(glow::UNIFORM_BUFFER, self.binding, Some(self.buffer)) }
+    }
+
+    pub fn binding(&self) -> u32 {
+        self.binding
+    }
+
+    pub fn buffer(&self) -> glow::NativeBuffer {
+        self.buffer
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Checks that the buffer is visible across the contexts: writes a
+    /// pattern in the headless context and reads it back in the window
+    /// context. Overwrites the start of the buffer, so run it before the
+    /// first real update. Neither context may be current. Buffers smaller
+    /// than the 4 byte pattern fail with [`SharingDiagnostic::TooSmall`].
+    pub fn check_sharing(&self, ctx: &mut ContextWrapper) -> Result<(), SharingDiagnostic> {
+        const PATTERN: [u8; 4] = [12, 34, 56, 78];
+        if self.size < PATTERN.len() {
+            return Err(SharingDiagnostic::TooSmall(self.size));
+        }
+
+        let c = ctx.try_ct_head()?;
+        let glh = ctx.gl(ContextId::Headless);
+        // the size was checked above
+        let _ = self.update(glh, &PATTERN);
+        // make the write visible to the other contexts
+        unsafe { glh.finish() };
+        ctx.put_head(c)?;
+
+        let c = ctx.try_ct_wnd()?;
+        let glw = ctx.gl(ContextId::Window);
+        let mut read = [0; 4];
+        unsafe {
+            glw.bind_buffer(glow::UNIFORM_BUFFER, Some(self.buffer));
+            let ptr = glw.map_buffer_range(
+                glow::UNIFORM_BUFFER,
+                0,
+                PATTERN.len() as i32,
+                glow::MAP_READ_BIT,
+            );
+            if !ptr.is_null() {
+                std::ptr::copy_nonoverlapping(ptr, read.as_mut_ptr(), read.len());
+                glw.unmap_buffer(glow::UNIFORM_BUFFER);
+            }
+            glw.bind_buffer(glow::UNIFORM_BUFFER, None);
+        }
+        ctx.put_wnd(c)?;
+
+        if read != PATTERN {
+            return Err(SharingDiagnostic::NotShared("window"));
+        }
+        Ok(())
+    }
+}
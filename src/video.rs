@@ -0,0 +1,97 @@
+//! Recording frames as uncompressed YUV4MPEG2, which ffmpeg, mpv and most
+//! encoders read directly, e.g. `ffmpeg -i out.y4m out.mp4`.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// Writes RGBA8 frames of a fixed size to a Y4M stream.
+pub struct Recorder {
+    out: Box<dyn Write>,
+    fps: u32,
+    size: Option<(u32, u32)>,
+    frames: u64,
+}
+
+impl Recorder {
+    /// Records to `path`, or to stdout if it is `-`. The header is written
+    /// with the first frame, which determines the size of the recording.
+    pub fn create(path: impl AsRef<Path>, fps: u32) -> io::Result<Self> {
+        let path = path.as_ref();
+        let out: Box<dyn Write> = if path == Path::new("-") {
+            Box::new(BufWriter::new(io::stdout()))
+        } else {
+            Box::new(BufWriter::new(File::create(path)?))
+        };
+        Ok(Self {
+            out,
+            fps,
+            size: None,
+            frames: 0,
+        })
+    }
+
+    /// Appends a frame with rows ordered top to bottom, as returned by
+    /// [`read_rgba`](crate::capture::read_rgba).
+    pub fn write_frame(&mut self, rgba: &[u8], width: u32, height: u32) -> io::Result<()> {
+        if rgba.len() != width as usize * height as usize * 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame data does not match its size",
+            ));
+        }
+        match self.size {
+            None => {
+                writeln!(
+                    self.out,
+                    "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+                    width, height, self.fps
+                )?;
+                self.size = Some((width, height));
+            }
+            Some(size) if size != (width, height) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "frame is {}x{}, the recording {}x{}",
+                        width, height, size.0, size.1
+                    ),
+                ));
+            }
+            Some(_) => {}
+        }
+
+        let pixels = rgba.len() / 4;
+        let mut planes = vec![0; pixels * 3];
+        let (y, uv) = planes.split_at_mut(pixels);
+        let (u, v) = uv.split_at_mut(pixels);
+        for (i, px) in rgba.chunks_exact(4).enumerate() {
+            [y[i], u[i], v[i]] = rgb_to_yuv(px[0], px[1], px[2]);
+        }
+        self.out.write_all(b"FRAME\n")?;
+        self.out.write_all(&planes)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Flushes the stream, returning the number of frames written.
+    pub fn finish(mut self) -> io::Result<u64> {
+        self.out.flush()?;
+        Ok(self.frames)
+    }
+}
+
+/// BT.601 limited range, which Y4M readers assume without a color tag.
+fn rgb_to_yuv(r: u8, g: u8, b: u8) -> [u8; 3] {
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let u = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let v = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    [y as u8, u as u8, v as u8]
+}
@@ -0,0 +1,348 @@
+//! Presenting one shared render across several fullscreen windows, one per
+//! monitor, each showing its own crop of it.
+
+use std::{convert::Infallible, num::NonZeroU32};
+
+use glow::HasContext;
+use glutin::{
+    context::{ContextAttributesBuilder, NotCurrentContext, PossiblyCurrentContext},
+    error::ErrorKind,
+    prelude::{GlDisplay, NotCurrentGlContextSurfaceAccessor, PossiblyCurrentGlContext},
+    surface::{GlSurface, Surface, WindowSurface},
+};
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+use winit::{
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
+    window::{Fullscreen, Window, WindowBuilder, WindowId},
+};
+
+use crate::{
+    app::{fail, AppError},
+    context::{create_shared_contexts, load_gl},
+    display::{create_surface, select_display_config, ColorDepth, DisplayConfig},
+    monitor::MonitorSelector,
+    present::{blit_to_window, AspectMode},
+    rect::Rect,
+    surface::{create_headless_surface, present, AnySurface, HeadlessSurface},
+};
+
+/// A monitor and the part of the shared render it shows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Screen {
+    pub monitor: MonitorSelector,
+    /// Source rect in the shared render, stretched to the whole monitor.
+    pub src: Rect,
+}
+
+/// Size of the shared render and how it is split across monitors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub size: (u32, u32),
+    pub screens: Vec<Screen>,
+}
+
+impl Layout {
+    /// Spans the render over all monitors as they are arranged on the
+    /// desktop, with one pixel of the render per pixel of the monitors.
+    pub fn desktop<T>(target: &EventLoopWindowTarget<T>) -> Self {
+        let monitors: Vec<_> = target
+            .available_monitors()
+            .map(|m| (m.position(), m.size()))
+            .collect();
+        let x0 = monitors.iter().map(|(pos, _)| pos.x).min().unwrap_or(0);
+        let y0 = monitors.iter().map(|(pos, _)| pos.y).min().unwrap_or(0);
+        let x1 = monitors
+            .iter()
+            .map(|(pos, size)| pos.x + size.width as i32)
+            .max()
+            .unwrap_or(0);
+        let y1 = monitors
+            .iter()
+            .map(|(pos, size)| pos.y + size.height as i32)
+            .max()
+            .unwrap_or(0);
+        let (width, height) = ((x1 - x0) as u32, (y1 - y0) as u32);
+
+        let screens = monitors
+            .iter()
+            .enumerate()
+            .map(|(i, (pos, size))| Screen {
+                monitor: MonitorSelector::Index(i),
+                // the desktop origin is top left, gl is bottom left
+                src: Rect::new(
+                    pos.x - x0,
+                    height as i32 - (pos.y - y0) - size.height as i32,
+                    size.width,
+                    size.height,
+                ),
+            })
+            .collect();
+        Self {
+            size: (width, height),
+            screens,
+        }
+    }
+}
+
+struct WallWindow {
+    window: Window,
+    surface: Surface<WindowSurface>,
+    context: Option<NotCurrentContext>,
+    gl: glow::Context,
+    fb: glow::NativeFramebuffer,
+    src: Rect,
+    size: (u32, u32),
+}
+
+impl WallWindow {
+    fn make_current(&mut self) -> glutin::error::Result<PossiblyCurrentContext> {
+        // a context that failed to switch is gone
+        let context = self.context.take().ok_or(ErrorKind::BadContextState)?;
+        context.make_current(&self.surface)
+    }
+
+    fn make_not_current(&mut self, c: PossiblyCurrentContext) -> glutin::error::Result<()> {
+        self.context = Some(c.make_not_current()?);
+        Ok(())
+    }
+
+    fn blit(&mut self) -> glutin::error::Result<()> {
+        let c = self.make_current()?;
+        let (width, height) = self.size;
+        let blitted = blit_to_window(
+            &self.gl,
+            self.fb,
+            self.src,
+            Rect::from_size(width, height),
+            AspectMode::Stretch,
+            [0.0, 0.0, 0.0, 1.0],
+        );
+        if let Err(err) = blitted {
+            eprintln!("screen not presented: {}", err);
+        }
+        let presented = present(&self.surface, &c, &self.gl);
+        self.make_not_current(c)?;
+        presented
+    }
+
+    fn resize(&mut self, width: u32, height: u32) -> glutin::error::Result<()> {
+        let c = self.make_current()?;
+        if let (Some(w), Some(h)) = (NonZeroU32::new(width), NonZeroU32::new(height)) {
+            self.surface.resize(&c, w, h);
+        }
+        self.make_not_current(c)?;
+        self.size = (width, height);
+        Ok(())
+    }
+}
+
+/// The headless side: the shared renderbuffer and a framebuffer rendering to it.
+struct Renderer {
+    surface: AnySurface,
+    context: Option<NotCurrentContext>,
+    gl: glow::Context,
+    renderbuffer: glow::NativeRenderbuffer,
+    fb: glow::NativeFramebuffer,
+    size: (u32, u32),
+}
+
+impl Renderer {
+    fn render(&mut self, render: &mut dyn FnMut(&glow::Context)) -> glutin::error::Result<()> {
+        let context = self.context.take().ok_or(ErrorKind::BadContextState)?;
+        let c = self.surface.make_current(context)?;
+        let gl = &self.gl;
+        unsafe { gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.fb)) };
+        Rect::from_size(self.size.0, self.size.1).viewport(gl);
+        render(gl);
+        unsafe {
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            gl.flush();
+        }
+        self.context = Some(c.make_not_current()?);
+        Ok(())
+    }
+
+    fn delete(&mut self) -> glutin::error::Result<()> {
+        let context = self.context.take().ok_or(ErrorKind::BadContextState)?;
+        let c = self.surface.make_current(context)?;
+        unsafe {
+            self.gl.delete_framebuffer(self.fb);
+            self.gl.delete_renderbuffer(self.renderbuffer);
+        }
+        c.make_not_current()?;
+        Ok(())
+    }
+}
+
+/// Opens a borderless fullscreen window on every monitor of `layout` and runs
+/// the event loop. `render` draws the whole shared render once per frame in a
+/// headless context, with its framebuffer bound, then every window blits its
+/// [`Screen::src`] from it. Screens whose monitor does not exist are skipped.
+///
+/// Only returns if the setup fails, like [`AppBuilder::run`](crate::AppBuilder::run).
+pub fn span_monitors(
+    event_loop: EventLoop<()>,
+    layout: Layout,
+    mut render: impl FnMut(&glow::Context) + 'static,
+) -> Result<Infallible, AppError> {
+    let mut windows = Vec::new();
+    for screen in &layout.screens {
+        let monitor = match screen.monitor.find(&event_loop) {
+            Some(monitor) => monitor,
+            None => continue,
+        };
+        let size = monitor.size();
+        let window = WindowBuilder::new()
+            .with_fullscreen(Some(Fullscreen::Borderless(Some(monitor))))
+            .build(&event_loop)?;
+        windows.push((window, screen.src, (size.width, size.height)));
+    }
+    let first_wnd = match windows.first() {
+        Some((window, ..)) => window.raw_window_handle(),
+        None => return Err(AppError::NoMonitor),
+    };
+
+    let DisplayConfig {
+        display,
+        config,
+        color_depth,
+        ..
+    } = select_display_config(
+        event_loop.raw_display_handle(),
+        first_wnd,
+        ColorDepth::Srgb8,
+        0,
+        Some((0, 0)),
+    )?;
+
+    let headless_surface =
+        create_headless_surface(&display, &config, first_wnd, HeadlessSurface::Auto)?;
+    let (first_context, headless) = create_shared_contexts(
+        &display,
+        &config,
+        None,
+        first_wnd,
+        &headless_surface,
+        Default::default(),
+    )?;
+
+    let c = headless_surface.make_current(headless)?;
+    let glh = load_gl(&c);
+    let objects = unsafe {
+        glh.create_renderbuffer().and_then(|rb| {
+            glh.bind_renderbuffer(glow::RENDERBUFFER, Some(rb));
+            glh.renderbuffer_storage(
+                glow::RENDERBUFFER,
+                color_depth.renderbuffer_format(),
+                layout.size.0 as _,
+                layout.size.1 as _,
+            );
+            let fb = glh.create_framebuffer()?;
+            glh.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+            glh.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::RENDERBUFFER,
+                Some(rb),
+            );
+            glh.bind_framebuffer(glow::FRAMEBUFFER, None);
+            Ok((rb, fb))
+        })
+    };
+    let headless = c.make_not_current()?;
+    let (renderbuffer, fb) = objects.map_err(AppError::Gl)?;
+
+    let mut first_context = Some(first_context);
+    let mut wall = Vec::with_capacity(windows.len());
+    for (window, src, (width, height)) in windows {
+        let raw_wnd = window.raw_window_handle();
+        let context = match first_context.take() {
+            Some(context) => context,
+            None => unsafe {
+                let attrs = ContextAttributesBuilder::new()
+                    .with_sharing(&headless)
+                    .build_windowed(raw_wnd);
+                display.create_context(&config, &attrs)?
+            },
+        };
+        let surface = create_surface(width, height, &display, &config, raw_wnd)?;
+        let c = context.make_current(&surface)?;
+        let gl = load_gl(&c);
+        let window_fb = unsafe {
+            gl.create_framebuffer().map(|fb| {
+                gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+                gl.framebuffer_renderbuffer(
+                    glow::FRAMEBUFFER,
+                    glow::COLOR_ATTACHMENT0,
+                    glow::RENDERBUFFER,
+                    Some(renderbuffer),
+                );
+                gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+                fb
+            })
+        };
+        let context = Some(c.make_not_current()?);
+        wall.push(WallWindow {
+            window,
+            surface,
+            context,
+            gl,
+            fb: window_fb.map_err(AppError::Gl)?,
+            src,
+            size: (width, height),
+        });
+    }
+
+    let mut renderer = Renderer {
+        surface: headless_surface,
+        context: Some(headless),
+        gl: glh,
+        renderbuffer,
+        fb,
+        size: layout.size,
+    };
+    event_loop.run(move |event, _, cf| {
+        *cf = ControlFlow::Wait;
+
+        match event {
+            Event::WindowEvent { window_id, event } => match event {
+                WindowEvent::Resized(size) => {
+                    if let Some(w) = find(&mut wall, window_id) {
+                        if let Err(err) = w.resize(size.width, size.height) {
+                            return fail(cf, "failed to resize", &err);
+                        }
+                        w.window.request_redraw();
+                    }
+                }
+                WindowEvent::CloseRequested => *cf = ControlFlow::Exit,
+                _ => {}
+            },
+            // every window shows the same frame, so any redraw draws them all
+            Event::RedrawRequested(_) => {
+                if let Err(err) = renderer.render(&mut render) {
+                    return fail(cf, "failed to render", &err);
+                }
+                for w in &mut wall {
+                    if let Err(err) = w.blit() {
+                        return fail(cf, "failed to present", &err);
+                    }
+                }
+            }
+            Event::LoopDestroyed => {
+                for w in &mut wall {
+                    if let Ok(c) = w.make_current() {
+                        unsafe { w.gl.delete_framebuffer(w.fb) };
+                        let _ = w.make_not_current(c);
+                    }
+                }
+                let _ = renderer.delete();
+            }
+            _ => {}
+        }
+    })
+}
+
+fn find(windows: &mut [WallWindow], id: WindowId) -> Option<&mut WallWindow> {
+    windows.iter_mut().find(|w| w.window.id() == id)
+}
@@ -0,0 +1,121 @@
+use std::ffi::{c_void, CStr};
+
+use glutin::{
+    context::RawContext,
+    display::{AsRawDisplay, Display, RawDisplay},
+    prelude::GlDisplay,
+};
+
+const RTLD_LAZY: i32 = 1;
+
+type XSync = unsafe extern "C" fn(display: *const c_void, discard: i32) -> i32;
+
+#[link(name = "dl")]
+extern "C" {
+    fn dlopen(filename: *const i8, flags: i32) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const i8) -> *mut c_void;
+}
+
+/// `XSync` from the libX11 winit and glutin already loaded, not linked so
+/// Wayland only systems without libX11 keep working.
+fn xsync() -> Option<XSync> {
+    let name = CStr::from_bytes_with_nul(b"libX11.so.6\0").ok()?;
+    let sym = CStr::from_bytes_with_nul(b"XSync\0").ok()?;
+    unsafe {
+        let lib = dlopen(name.as_ptr(), RTLD_LAZY);
+        if lib.is_null() {
+            return None;
+        }
+        let f = dlsym(lib, sym.as_ptr());
+        if f.is_null() {
+            return None;
+        }
+        Some(std::mem::transmute_copy(&f))
+    }
+}
+
+type GlxQueryExtensionsString =
+    unsafe extern "C" fn(display: *const c_void, screen: i32) -> *const i8;
+
+/// Whether a GLX display advertises `name` for the default screen 0.
+/// Always `false` on other displays.
+pub fn glx_has_extension(display: &Display, name: &str) -> bool {
+    let xdisplay = match display.raw_display() {
+        RawDisplay::Glx(xdisplay) => xdisplay,
+        #[allow(unreachable_patterns)]
+        _ => return false,
+    };
+    let f = match CStr::from_bytes_with_nul(b"glXQueryExtensionsString\0") {
+        Ok(sym) => display.get_proc_address(sym),
+        Err(_) => return false,
+    };
+    if f.is_null() {
+        return false;
+    }
+    let query: GlxQueryExtensionsString = unsafe { std::mem::transmute_copy(&f) };
+    let extensions = unsafe { query(xdisplay, 0) };
+    if extensions.is_null() {
+        return false;
+    }
+    let extensions = unsafe { CStr::from_ptr(extensions) }.to_string_lossy();
+    extensions.split_ascii_whitespace().any(|ext| ext == name)
+}
+
+type GlxGetCurrentContext = unsafe extern "C" fn() -> *const c_void;
+
+/// The GLX context the driver has current on the calling thread, null if
+/// none. `None` for other displays.
+pub(crate) fn glx_current_context(display: &Display) -> Option<*const c_void> {
+    match display.raw_display() {
+        RawDisplay::Glx(_) => {}
+        #[allow(unreachable_patterns)]
+        _ => return None,
+    }
+    let sym = CStr::from_bytes_with_nul(b"glXGetCurrentContext\0").ok()?;
+    let f = display.get_proc_address(sym);
+    if f.is_null() {
+        return None;
+    }
+    let get_current_context: GlxGetCurrentContext = unsafe { std::mem::transmute_copy(&f) };
+    Some(unsafe { get_current_context() })
+}
+
+type GlxIsDirect = unsafe extern "C" fn(display: *const c_void, context: *const c_void) -> i32;
+
+/// `glXIsDirect` for a context of a GLX display, `None` for other displays or
+/// if the entry point is missing. An indirect context sends every call through
+/// the X server, as over ssh X forwarding, and lacks framebuffer objects and
+/// `glBlitFramebuffer`.
+pub fn glx_is_direct(display: &Display, context: RawContext) -> Option<bool> {
+    let (xdisplay, context) = match (display.raw_display(), context) {
+        (RawDisplay::Glx(xdisplay), RawContext::Glx(context)) => (xdisplay, context),
+        #[allow(unreachable_patterns)]
+        _ => return None,
+    };
+    let sym = CStr::from_bytes_with_nul(b"glXIsDirect\0").ok()?;
+    let f = display.get_proc_address(sym);
+    if f.is_null() {
+        return None;
+    }
+    let is_direct: GlxIsDirect = unsafe { std::mem::transmute_copy(&f) };
+    Some(unsafe { is_direct(xdisplay, context) } != 0)
+}
+
+/// Waits for the X server to process every request sent on the connection of
+/// a GLX display, so X errors they caused reach the registered xlib error
+/// hook now instead of being reported during some later, unrelated call.
+/// Returns `false` without doing anything for other displays.
+pub fn pump_errors(display: &Display) -> bool {
+    let xdisplay = match display.raw_display() {
+        RawDisplay::Glx(xdisplay) => xdisplay,
+        #[allow(unreachable_patterns)]
+        _ => return false,
+    };
+    match xsync() {
+        Some(xsync) => {
+            unsafe { xsync(xdisplay, 0) };
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,80 @@
+//! Attaches a depth/stencil renderbuffer to a shared target and checks that
+//! clearing it applies the configured stencil value.
+
+mod common;
+
+use glow::HasContext;
+use glutin2_sharing::{ContextId, GlCaps, SharedTarget};
+
+const STENCIL: u8 = 0x5A;
+
+#[test]
+#[ignore = "needs a GPU"]
+fn clear_stencil() {
+    // bound in reverse so the wrapper is dropped before the event loop
+    let common::Shared {
+        event_loop: _event_loop,
+        window: _window,
+        mut ctx,
+    } = common::shared_contexts();
+    let (glw, glh) = (
+        ctx.gl_handle(ContextId::Window),
+        ctx.gl_handle(ContextId::Headless),
+    );
+    let mut target = SharedTarget::new(&mut ctx, &glw, &glh, glow::RGBA8, 16, 16).unwrap();
+    target.set_clear_stencil(STENCIL as i32);
+
+    let c = ctx.try_ct_head().unwrap();
+    let caps = GlCaps::query(&glh);
+    let (mask, stencil) = unsafe {
+        let rb = glh.create_renderbuffer().unwrap();
+        glh.bind_renderbuffer(glow::RENDERBUFFER, Some(rb));
+        glh.renderbuffer_storage(glow::RENDERBUFFER, glow::DEPTH24_STENCIL8, 16, 16);
+        glh.bind_framebuffer(glow::FRAMEBUFFER, Some(target.render_fb()));
+        glh.framebuffer_renderbuffer(
+            glow::FRAMEBUFFER,
+            glow::DEPTH_STENCIL_ATTACHMENT,
+            glow::RENDERBUFFER,
+            Some(rb),
+        );
+        assert_eq!(
+            glh.check_framebuffer_status(glow::FRAMEBUFFER),
+            glow::FRAMEBUFFER_COMPLETE
+        );
+
+        let mask = target.clear_mask(&glh);
+        target.clear(&glh);
+        // GLES cannot read back stencil values
+        let mut stencil = [0];
+        if !caps.is_gles() {
+            glh.read_pixels(
+                0,
+                0,
+                1,
+                1,
+                glow::STENCIL_INDEX,
+                glow::UNSIGNED_BYTE,
+                glow::PixelPackData::Slice(&mut stencil),
+            );
+        }
+
+        glh.framebuffer_renderbuffer(
+            glow::FRAMEBUFFER,
+            glow::DEPTH_STENCIL_ATTACHMENT,
+            glow::RENDERBUFFER,
+            None,
+        );
+        glh.bind_framebuffer(glow::FRAMEBUFFER, None);
+        glh.delete_renderbuffer(rb);
+        (mask, stencil[0])
+    };
+    ctx.put_head(c).unwrap();
+
+    assert_ne!(mask & glow::STENCIL_BUFFER_BIT, 0);
+    assert_ne!(mask & glow::DEPTH_BUFFER_BIT, 0);
+    if !caps.is_gles() {
+        assert_eq!(stencil, STENCIL);
+    }
+
+    ctx.shutdown();
+}
@@ -0,0 +1,76 @@
+//! Setup shared by the GPU tests. These need a display and are `#[ignore]`d,
+//! run them with `cargo test -- --ignored`.
+
+#[cfg(feature = "winit-027")]
+use glutin2_sharing::winit::platform::unix::EventLoopBuilderExtUnix;
+#[cfg(feature = "winit-028")]
+use glutin2_sharing::winit::platform::x11::EventLoopBuilderExtX11;
+use glutin2_sharing::{
+    create_headless_surface, create_shared_contexts, create_surface, select_display_config,
+    winit::{
+        event_loop::{EventLoop, EventLoopBuilder},
+        window::{Window, WindowBuilder},
+    },
+    ColorDepth, ContextOptions, ContextWrapper, DisplayConfig, HeadlessSurface,
+};
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+
+/// Everything a test needs to talk to both contexts. `ctx` is declared first
+/// so it is dropped before the window and the event loop it was created from.
+pub struct Shared {
+    pub ctx: ContextWrapper,
+    pub window: Window,
+    pub event_loop: EventLoop<()>,
+}
+
+/// Creates a hidden window and a context wrapper for it. The test harness
+/// runs every test on its own thread, so the event loop is allowed off the
+/// main thread; keep to one test per file, winit only allows one event loop
+/// per process.
+pub fn shared_contexts() -> Shared {
+    let event_loop = EventLoopBuilder::new().with_any_thread(true).build();
+    let window = WindowBuilder::new()
+        .with_visible(false)
+        .build(&event_loop)
+        .unwrap();
+    let raw_wnd = window.raw_window_handle();
+    let size = window.inner_size();
+
+    let DisplayConfig {
+        display, config, ..
+    } = select_display_config(
+        event_loop.raw_display_handle(),
+        raw_wnd,
+        ColorDepth::Srgb8,
+        0,
+        Some((0, 0)),
+    )
+    .unwrap();
+    let window_surface =
+        create_surface(size.width, size.height, &display, &config, raw_wnd).unwrap();
+    let headless_surface =
+        create_headless_surface(&display, &config, raw_wnd, HeadlessSurface::Auto).unwrap();
+    let (window_context, headless_context) = create_shared_contexts(
+        &display,
+        &config,
+        None,
+        raw_wnd,
+        &headless_surface,
+        ContextOptions::default(),
+    )
+    .unwrap();
+    let ctx = ContextWrapper::new(
+        display,
+        window_surface,
+        headless_surface,
+        window_context,
+        headless_context,
+    )
+    .unwrap();
+
+    Shared {
+        ctx,
+        window,
+        event_loop,
+    }
+}
@@ -0,0 +1,91 @@
+//! Renders a known pattern offscreen and compares it against the reference
+//! image of `examples/golden.rs`.
+
+mod common;
+
+use glow::HasContext;
+use glutin2_sharing::{
+    capture::{diff_rgba, load_rgba, read_rgba},
+    ContextId, Rect,
+};
+
+const REFERENCE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/examples/reference/quadrants.png"
+);
+const TOLERANCE: u8 = 2;
+
+fn draw_quadrants(gl: &glow::Context, width: u32, height: u32) {
+    let (hw, hh) = (width / 2, height / 2);
+    // gl origin is bottom left, the reference image is top left
+    let quadrants = [
+        (Rect::new(0, hh as i32, hw, hh), [1.0, 0.0, 0.0]),
+        (Rect::new(hw as i32, hh as i32, hw, hh), [0.0, 1.0, 0.0]),
+        (Rect::new(0, 0, hw, hh), [0.0, 0.0, 1.0]),
+        (Rect::new(hw as i32, 0, hw, hh), [1.0, 1.0, 1.0]),
+    ];
+    unsafe {
+        gl.enable(glow::SCISSOR_TEST);
+        for (rect, [r, g, b]) in quadrants {
+            rect.scissor(gl);
+            gl.clear_color(r, g, b, 1.0);
+            gl.clear(glow::COLOR_BUFFER_BIT);
+        }
+        gl.disable(glow::SCISSOR_TEST);
+    }
+}
+
+#[test]
+#[ignore = "needs a GPU"]
+fn golden_quadrants() {
+    let (reference, width, height) = load_rgba(REFERENCE).unwrap();
+
+    // bound in reverse so the wrapper is dropped before the event loop
+    let common::Shared {
+        event_loop: _event_loop,
+        window: _window,
+        mut ctx,
+    } = common::shared_contexts();
+
+    let c = ctx.try_ct_head().unwrap();
+    let gl = ctx.gl(ContextId::Headless);
+    let pixels = unsafe {
+        let rb = gl.create_renderbuffer().unwrap();
+        gl.bind_renderbuffer(glow::RENDERBUFFER, Some(rb));
+        gl.renderbuffer_storage(glow::RENDERBUFFER, glow::RGBA8, width as _, height as _);
+        let fb = gl.create_framebuffer().unwrap();
+        gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fb));
+        gl.framebuffer_renderbuffer(
+            glow::FRAMEBUFFER,
+            glow::COLOR_ATTACHMENT0,
+            glow::RENDERBUFFER,
+            Some(rb),
+        );
+        Rect::from_size(width, height).viewport(gl);
+
+        draw_quadrants(gl, width, height);
+        let pixels = read_rgba(gl, width, height);
+
+        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+        gl.delete_framebuffer(fb);
+        gl.delete_renderbuffer(rb);
+        pixels
+    };
+    ctx.put_head(c).unwrap();
+
+    let diff = diff_rgba(&pixels, &reference).unwrap();
+    assert!(
+        diff.max <= TOLERANCE,
+        "max difference {} exceeds {}",
+        diff.max,
+        TOLERANCE
+    );
+    assert!(
+        diff.mean <= TOLERANCE as f64,
+        "mean difference {:.4} exceeds {}",
+        diff.mean,
+        TOLERANCE
+    );
+
+    ctx.shutdown();
+}
@@ -0,0 +1,98 @@
+//! Drives the resize path of a shared target outside any event loop, through
+//! degenerate and large sizes, and checks that the storage read back in the
+//! headless context always has the size the target reports.
+
+mod common;
+
+use glow::HasContext;
+use glutin2_sharing::{
+    capture::read_rgba, drain_gl_errors, ContextId, ContextWrapper, GlCaps, SharedTarget,
+};
+
+const CLEAR: [u8; 4] = [255, 0, 255, 255];
+
+/// Clears the headless framebuffer and reads all of it back, asserting that
+/// the readback covers the whole target, is cleared and raised no GL error.
+fn check_readback(ctx: &mut ContextWrapper, target: &SharedTarget) {
+    let (width, height) = target.size();
+    let c = ctx.try_ct_head().unwrap();
+    let glh = ctx.gl(ContextId::Headless);
+    let pixels = unsafe {
+        drain_gl_errors(glh);
+        glh.bind_framebuffer(glow::FRAMEBUFFER, Some(target.headless_fb()));
+        glh.disable(glow::SCISSOR_TEST);
+        glh.clear_color(1.0, 0.0, 1.0, 1.0);
+        glh.clear(glow::COLOR_BUFFER_BIT);
+        let pixels = read_rgba(glh, width, height);
+        glh.bind_framebuffer(glow::FRAMEBUFFER, None);
+        pixels
+    };
+    let errors = drain_gl_errors(glh);
+    ctx.put_head(c).unwrap();
+
+    assert_eq!(
+        errors,
+        Vec::<u32>::new(),
+        "gl errors at {}x{}",
+        width,
+        height
+    );
+    assert_eq!(pixels.len(), width as usize * height as usize * 4);
+    assert_eq!(
+        pixels.chunks_exact(4).position(|pixel| pixel != CLEAR),
+        None,
+        "stale pixel at {}x{}",
+        width,
+        height
+    );
+}
+
+#[test]
+#[ignore = "needs a GPU"]
+fn resize_readback() {
+    // bound in reverse so the wrapper is dropped before the event loop
+    let common::Shared {
+        event_loop: _event_loop,
+        window,
+        mut ctx,
+    } = common::shared_contexts();
+    let size = window.inner_size();
+
+    let (glw, glh) = (
+        ctx.gl_handle(ContextId::Window),
+        ctx.gl_handle(ContextId::Headless),
+    );
+    let c = ctx.try_ct_head().unwrap();
+    let large = GlCaps::query(&glh).max_renderbuffer_size().min(4096);
+    ctx.put_head(c).unwrap();
+    let mut target =
+        SharedTarget::new(&mut ctx, &glw, &glh, glow::RGBA8, size.width, size.height).unwrap();
+
+    let sizes = [
+        (0, 0),
+        (1, 1),
+        (0, 1),
+        (large, large),
+        (1, large),
+        (640, 480),
+    ];
+    for (width, height) in sizes {
+        // the window surface keeps the size of the window, the target follows it
+        let before = target.size();
+        let surface = ctx.resize((width, height), Some(&mut target)).unwrap();
+        if width == 0 || height == 0 {
+            assert_eq!(target.size(), before, "zero size resized the target");
+            check_readback(&mut ctx, &target);
+            // zero sizes only ever reach the target through the wrapper
+            continue;
+        }
+        assert_eq!(target.size(), surface);
+        check_readback(&mut ctx, &target);
+
+        target.resize(&mut ctx, &glw, width, height).unwrap();
+        assert_eq!(target.size(), (width, height));
+        check_readback(&mut ctx, &target);
+    }
+
+    ctx.shutdown();
+}
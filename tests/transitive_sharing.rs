@@ -0,0 +1,21 @@
+//! Creates a texture in the window context and reads it back in the headless
+//! root and in a third context that only shares with the root.
+
+mod common;
+
+#[test]
+#[ignore = "needs a GPU"]
+fn transitive_sharing() {
+    // bound in reverse so the wrapper is dropped before the event loop
+    let common::Shared {
+        event_loop: _event_loop,
+        window: _window,
+        mut ctx,
+    } = common::shared_contexts();
+
+    if let Err(err) = ctx.check_transitive_sharing() {
+        panic!("{}", err);
+    }
+
+    ctx.shutdown();
+}
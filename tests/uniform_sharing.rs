@@ -0,0 +1,31 @@
+//! Writes a uniform buffer in the headless context and reads it back in the
+//! window context.
+
+mod common;
+
+use glutin2_sharing::{uniform::SharedUniformBuffer, ContextId, SharingDiagnostic};
+
+#[test]
+#[ignore = "needs a GPU"]
+fn uniform_sharing() {
+    // bound in reverse so the wrapper is dropped before the event loop
+    let common::Shared {
+        event_loop: _event_loop,
+        window: _window,
+        mut ctx,
+    } = common::shared_contexts();
+    let glh = ctx.gl_handle(ContextId::Headless);
+
+    let buffer = SharedUniformBuffer::new(&mut ctx, &glh, 0, 64).unwrap();
+    if let Err(err) = buffer.check_sharing(&mut ctx) {
+        panic!("{}", err);
+    }
+
+    let small = SharedUniformBuffer::new(&mut ctx, &glh, 1, 2).unwrap();
+    assert!(matches!(
+        small.check_sharing(&mut ctx),
+        Err(SharingDiagnostic::TooSmall(2))
+    ));
+
+    ctx.shutdown();
+}